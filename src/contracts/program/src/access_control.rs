@@ -1,4 +1,11 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::keccak;
+use crate::platform_instructions::*;
+use crate::timelock_standards::require_no_timelock;
+use crate::Project;
+
+// Maximum number of role assignments stored on-chain
+pub const MAX_ROLES: usize = 64;
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq)]
 pub enum Role {
@@ -7,15 +14,27 @@ pub enum Role {
     Collaborator,
     Investor,
     User,
+    // Rules on exchange disputes
+    Arbiter,
+    // Signing authority of the platform's governance program
+    Governance,
+    // Issues transfer authorizations for restricted tokens
+    Compliance,
+    // May pause the platform in an emergency
+    Guardian,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq)]
+// Platform-wide role registry, a singleton PDA at ["access_control"]
+#[account]
+#[derive(PartialEq)]
 pub struct AccessControl {
     pub admin: Pubkey,
     pub roles: Vec<(Pubkey, Role)>,
 }
 
 impl AccessControl {
+    pub const LEN: usize = 8 + 32 + 4 + MAX_ROLES * (32 + 1);
+
     pub fn new(admin: Pubkey) -> Self {
         Self {
             admin,
@@ -36,6 +55,15 @@ impl AccessControl {
         }
     }
 
+    // add_role bounded by the registry's reserved space
+    pub fn grant(&mut self, user: Pubkey, role: Role) -> Result<()> {
+        if self.roles.len() >= MAX_ROLES {
+            return Err(error!(AccessControlError::RoleLimitReached));
+        }
+        self.add_role(user, role);
+        Ok(())
+    }
+
     pub fn remove_role(&mut self, user: &Pubkey, role: &Role) {
         self.roles.retain(|(u, r)| u != user || r != role);
     }
@@ -45,6 +73,63 @@ impl AccessControl {
     }
 }
 
+// Investor allowlist of a project, PDA at ["investor_allowlist", project].
+// Large lists are published as a Merkle root that investors prove against.
+#[account]
+pub struct InvestorAllowlist {
+    pub project: Pubkey,
+    pub merkle_root: Option<[u8; 32]>,
+    pub bump: u8,
+}
+
+impl InvestorAllowlist {
+    pub const LEN: usize = 8 + 32 + 33 + 1;
+}
+
+// A wallet approved to invest in a project,
+// PDA at ["project_investor", project, investor]
+#[account]
+pub struct ProjectInvestor {
+    pub project: Pubkey,
+    pub investor: Pubkey,
+    pub granted_at: i64,
+    pub bump: u8,
+}
+
+impl ProjectInvestor {
+    pub const LEN: usize = 8 + 32 + 32 + 8 + 1;
+}
+
+// Leaf of an investor Merkle allowlist
+pub fn investor_leaf(investor: &Pubkey) -> [u8; 32] {
+    keccak::hashv(&[investor.as_ref()]).to_bytes()
+}
+
+// Verify a Merkle proof built with sorted-pair keccak hashing
+pub fn verify_merkle_proof(root: &[u8; 32], leaf: [u8; 32], proof: &[[u8; 32]]) -> bool {
+    let computed = proof.iter().fold(leaf, |node, sibling| {
+        if node <= *sibling {
+            keccak::hashv(&[&node, sibling]).to_bytes()
+        } else {
+            keccak::hashv(&[sibling, &node]).to_bytes()
+        }
+    });
+    computed == *root
+}
+
+#[event]
+pub struct InvestorGranted {
+    pub project: Pubkey,
+    pub investor: Pubkey,
+    pub via_merkle_proof: bool,
+}
+
+#[event]
+pub struct InvestorRevoked {
+    pub project: Pubkey,
+    pub investor: Pubkey,
+}
+
 // Access control errors
 #[error_code]
 pub enum AccessControlError {
@@ -54,6 +139,10 @@ pub enum AccessControlError {
     UnauthorizedCreator,
     #[msg("Unauthorized: Invalid role for this operation")]
     UnauthorizedRole,
+    #[msg("Role registry is full")]
+    RoleLimitReached,
+    #[msg("Invalid investor allowlist proof")]
+    InvalidAllowlistProof,
 }
 
 // Access control checks
@@ -76,4 +165,208 @@ pub fn require_role(access_control: &AccessControl, user: &Pubkey, role: &Role)
         return Err(error!(AccessControlError::UnauthorizedRole));
     }
     Ok(())
-}
\ No newline at end of file
+}
+
+// Investors are either platform-wide Role::Investor holders or approved on
+// the project's own allowlist
+pub fn require_investor(
+    access_control: Option<&AccessControl>,
+    approval: Option<&ProjectInvestor>,
+    project: &Pubkey,
+    user: &Pubkey,
+) -> Result<()> {
+    if approval.is_some_and(|approval| approval.project == *project && approval.investor == *user) {
+        return Ok(());
+    }
+    match access_control {
+        Some(access_control) => require_role(access_control, user, &Role::Investor),
+        None => Err(error!(AccessControlError::UnauthorizedRole)),
+    }
+}
+
+#[derive(Accounts)]
+pub struct InitializeAccessControl<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = AccessControl::LEN,
+        seeds = [b"access_control"],
+        bump
+    )]
+    pub access_control: Account<'info, AccessControl>,
+    // Only the program's upgrade authority may create the registry
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, crate::program::Neplus>,
+    #[account(constraint = program_data.upgrade_authority_address == Some(admin.key())
+        @ AccessControlError::UnauthorizedAdmin)]
+    pub program_data: Account<'info, ProgramData>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ManageRole<'info> {
+    #[account(mut, seeds = [b"access_control"], bump)]
+    pub access_control: Account<'info, AccessControl>,
+    /// CHECK: Timelock queue PDA, only checked for existence
+    #[account(seeds = [b"timelock_queue"], bump)]
+    pub timelock_queue: UncheckedAccount<'info>,
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetInvestorRoot<'info> {
+    #[account(has_one = creator)]
+    pub project: Account<'info, Project>,
+    #[account(
+        init_if_needed,
+        payer = creator,
+        space = InvestorAllowlist::LEN,
+        seeds = [b"investor_allowlist", project.key().as_ref()],
+        bump
+    )]
+    pub allowlist: Account<'info, InvestorAllowlist>,
+    pub platform: ProjectsLive<'info>,
+    #[account(mut)]
+    pub creator: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(investor: Pubkey)]
+pub struct GrantInvestor<'info> {
+    #[account(has_one = creator)]
+    pub project: Account<'info, Project>,
+    #[account(
+        init,
+        payer = creator,
+        space = ProjectInvestor::LEN,
+        seeds = [b"project_investor", project.key().as_ref(), investor.as_ref()],
+        bump
+    )]
+    pub approval: Account<'info, ProjectInvestor>,
+    pub platform: ProjectsLive<'info>,
+    #[account(mut)]
+    pub creator: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeInvestor<'info> {
+    #[account(has_one = creator)]
+    pub project: Account<'info, Project>,
+    #[account(
+        mut,
+        close = creator,
+        seeds = [b"project_investor", project.key().as_ref(), approval.investor.as_ref()],
+        bump = approval.bump
+    )]
+    pub approval: Account<'info, ProjectInvestor>,
+    pub platform: ProjectsLive<'info>,
+    #[account(mut)]
+    pub creator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct VerifyInvestor<'info> {
+    #[account(seeds = [b"investor_allowlist", allowlist.project.as_ref()], bump = allowlist.bump)]
+    pub allowlist: Account<'info, InvestorAllowlist>,
+    #[account(
+        init,
+        payer = investor,
+        space = ProjectInvestor::LEN,
+        seeds = [b"project_investor", allowlist.project.as_ref(), investor.key().as_ref()],
+        bump
+    )]
+    pub approval: Account<'info, ProjectInvestor>,
+    pub platform: ProjectsLive<'info>,
+    #[account(mut)]
+    pub investor: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handle_initialize_access_control(ctx: Context<InitializeAccessControl>) -> Result<()> {
+    let access_control = &mut ctx.accounts.access_control;
+    access_control.set_inner(AccessControl::new(ctx.accounts.admin.key()));
+    Ok(())
+}
+
+pub fn handle_grant_role(ctx: Context<ManageRole>, user: Pubkey, role: Role) -> Result<()> {
+    let access_control = &mut ctx.accounts.access_control;
+    require_admin(access_control, &ctx.accounts.admin.key())?;
+    require_no_timelock(&ctx.accounts.timelock_queue)?;
+
+    access_control.grant(user, role)
+}
+
+pub fn handle_revoke_role(ctx: Context<ManageRole>, user: Pubkey, role: Role) -> Result<()> {
+    let access_control = &mut ctx.accounts.access_control;
+    require_admin(access_control, &ctx.accounts.admin.key())?;
+    require_no_timelock(&ctx.accounts.timelock_queue)?;
+
+    access_control.remove_role(&user, &role);
+    Ok(())
+}
+
+// The project creator publishes or clears the Merkle root of its allowlist
+pub fn handle_set_investor_root(
+    ctx: Context<SetInvestorRoot>,
+    merkle_root: Option<[u8; 32]>,
+) -> Result<()> {
+    let allowlist = &mut ctx.accounts.allowlist;
+    allowlist.project = ctx.accounts.project.key();
+    allowlist.merkle_root = merkle_root;
+    allowlist.bump = *ctx.bumps.get("allowlist").unwrap();
+    Ok(())
+}
+
+pub fn handle_grant_investor(ctx: Context<GrantInvestor>, investor: Pubkey) -> Result<()> {
+    let approval = &mut ctx.accounts.approval;
+    approval.project = ctx.accounts.project.key();
+    approval.investor = investor;
+    approval.granted_at = Clock::get()?.unix_timestamp;
+    approval.bump = *ctx.bumps.get("approval").unwrap();
+
+    emit!(InvestorGranted {
+        project: approval.project,
+        investor,
+        via_merkle_proof: false,
+    });
+
+    Ok(())
+}
+
+pub fn handle_revoke_investor(ctx: Context<RevokeInvestor>) -> Result<()> {
+    emit!(InvestorRevoked {
+        project: ctx.accounts.approval.project,
+        investor: ctx.accounts.approval.investor,
+    });
+    Ok(())
+}
+
+// An investor on the Merkle allowlist proves membership once and receives
+// the same approval the creator would grant directly
+pub fn handle_verify_investor(ctx: Context<VerifyInvestor>, proof: Vec<[u8; 32]>) -> Result<()> {
+    let allowlist = &ctx.accounts.allowlist;
+    let investor = ctx.accounts.investor.key();
+
+    let root = allowlist.merkle_root.ok_or(AccessControlError::InvalidAllowlistProof)?;
+    if !verify_merkle_proof(&root, investor_leaf(&investor), &proof) {
+        return Err(error!(AccessControlError::InvalidAllowlistProof));
+    }
+
+    let approval = &mut ctx.accounts.approval;
+    approval.project = allowlist.project;
+    approval.investor = investor;
+    approval.granted_at = Clock::get()?.unix_timestamp;
+    approval.bump = *ctx.bumps.get("approval").unwrap();
+
+    emit!(InvestorGranted {
+        project: approval.project,
+        investor,
+        via_merkle_proof: true,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,633 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Mint, Token, TokenAccount};
+use crate::access_control::{AccessControl, AccessControlError, Role};
+use crate::exchange_standards::*;
+use crate::platform_instructions::*;
+use crate::platform_standards::{pay_with_fee, FeeCollected, PlatformConfig, PlatformError};
+use crate::*;
+
+#[derive(Accounts)]
+pub struct EscrowPurchase<'info> {
+    #[account(mut)]
+    pub resource: Account<'info, Resource>,
+    #[account(
+        init,
+        payer = buyer,
+        space = Escrow::LEN,
+        seeds = [
+            b"escrow",
+            resource.key().as_ref(),
+            buyer.key().as_ref(),
+            &resource.purchase_count.to_le_bytes(),
+        ],
+        bump
+    )]
+    pub escrow: Account<'info, Escrow>,
+    #[account(address = resource.payment_mint @ ResourceError::WrongPaymentMint)]
+    pub payment_mint: Account<'info, Mint>,
+    #[account(
+        init,
+        payer = buyer,
+        seeds = [b"escrow_vault", escrow.key().as_ref()],
+        bump,
+        token::mint = payment_mint,
+        token::authority = escrow
+    )]
+    pub vault: Account<'info, TokenAccount>,
+    #[account(mut, token::mint = payment_mint, token::authority = buyer)]
+    pub buyer_token_account: Account<'info, TokenAccount>,
+    pub platform: ExchangeLive<'info>,
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseEscrow<'info> {
+    #[account(
+        mut,
+        has_one = buyer,
+        seeds = [
+            b"escrow",
+            escrow.resource.as_ref(),
+            escrow.buyer.as_ref(),
+            &escrow.index.to_le_bytes(),
+        ],
+        bump = escrow.bump
+    )]
+    pub escrow: Account<'info, Escrow>,
+    #[account(mut, address = escrow.resource)]
+    pub resource: Account<'info, Resource>,
+    #[account(
+        init,
+        payer = authority,
+        space = Purchase::LEN,
+        seeds = [
+            b"purchase",
+            escrow.resource.as_ref(),
+            escrow.buyer.as_ref(),
+            &escrow.index.to_le_bytes(),
+        ],
+        bump
+    )]
+    pub purchase: Account<'info, Purchase>,
+    #[account(mut, seeds = [b"escrow_vault", escrow.key().as_ref()], bump)]
+    pub vault: Account<'info, TokenAccount>,
+    #[account(mut, token::mint = escrow.payment_mint, token::authority = escrow.provider)]
+    pub provider_token_account: Account<'info, TokenAccount>,
+    pub platform: ExchangeLive<'info>,
+    #[account(seeds = [b"access_control"], bump)]
+    pub access_control: Account<'info, AccessControl>,
+    #[account(
+        mut,
+        token::mint = escrow.payment_mint,
+        constraint = platform.platform_config.is_treasury(&escrow.payment_mint, &treasury_token_account.key())
+            @ PlatformError::UnknownTreasury
+    )]
+    pub treasury_token_account: Account<'info, TokenAccount>,
+    /// CHECK: Escrow buyer, receives the vault rent
+    #[account(mut)]
+    pub buyer: AccountInfo<'info>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct OpenDispute<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"escrow",
+            escrow.resource.as_ref(),
+            escrow.buyer.as_ref(),
+            &escrow.index.to_le_bytes(),
+        ],
+        bump = escrow.bump
+    )]
+    pub escrow: Account<'info, Escrow>,
+    #[account(
+        init,
+        payer = authority,
+        space = Dispute::LEN,
+        seeds = [b"dispute", escrow.key().as_ref()],
+        bump
+    )]
+    pub dispute: Account<'info, Dispute>,
+    pub platform: ExchangeLive<'info>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RespondDispute<'info> {
+    #[account(
+        mut,
+        has_one = escrow,
+        seeds = [b"dispute", escrow.key().as_ref()],
+        bump = dispute.bump
+    )]
+    pub dispute: Account<'info, Dispute>,
+    pub escrow: Account<'info, Escrow>,
+    pub platform: ExchangeLive<'info>,
+    pub authority: Signer<'info>,
+}
+
+// Open while the exchange is paused, so disputed escrows can always be settled
+#[derive(Accounts)]
+pub struct SettleDispute<'info> {
+    #[account(
+        mut,
+        has_one = escrow,
+        seeds = [b"dispute", escrow.key().as_ref()],
+        bump = dispute.bump
+    )]
+    pub dispute: Account<'info, Dispute>,
+    #[account(
+        mut,
+        has_one = buyer,
+        seeds = [
+            b"escrow",
+            escrow.resource.as_ref(),
+            escrow.buyer.as_ref(),
+            &escrow.index.to_le_bytes(),
+        ],
+        bump = escrow.bump
+    )]
+    pub escrow: Account<'info, Escrow>,
+    #[account(mut, address = escrow.resource)]
+    pub resource: Account<'info, Resource>,
+    // Receipt, required when the ruling pays the provider anything
+    #[account(
+        init,
+        payer = authority,
+        space = Purchase::LEN,
+        seeds = [
+            b"purchase",
+            escrow.resource.as_ref(),
+            escrow.buyer.as_ref(),
+            &escrow.index.to_le_bytes(),
+        ],
+        bump
+    )]
+    pub purchase: Option<Account<'info, Purchase>>,
+    #[account(mut, seeds = [b"escrow_vault", escrow.key().as_ref()], bump)]
+    pub vault: Account<'info, TokenAccount>,
+    #[account(mut, token::mint = escrow.payment_mint, token::authority = escrow.buyer)]
+    pub buyer_token_account: Account<'info, TokenAccount>,
+    #[account(mut, token::mint = escrow.payment_mint, token::authority = escrow.provider)]
+    pub provider_token_account: Account<'info, TokenAccount>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(
+        mut,
+        token::mint = escrow.payment_mint,
+        constraint = platform_config.is_treasury(&escrow.payment_mint, &treasury_token_account.key())
+            @ PlatformError::UnknownTreasury
+    )]
+    pub treasury_token_account: Account<'info, TokenAccount>,
+    /// CHECK: Escrow buyer, receives the vault rent
+    #[account(mut)]
+    pub buyer: AccountInfo<'info>,
+    #[account(seeds = [b"access_control"], bump)]
+    pub access_control: Account<'info, AccessControl>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+// Escrow instruction handlers
+pub fn handle_escrow_purchase(
+    ctx: Context<EscrowPurchase>,
+    quantity: u64,
+    inspection_period: i64,
+) -> Result<()> {
+    let resource = &mut ctx.accounts.resource;
+    let escrow = &mut ctx.accounts.escrow;
+    let now = Clock::get()?.unix_timestamp;
+
+    if resource.exchange_type != ExchangeType::Token {
+        return Err(error!(ExchangeError::WrongExchangeType));
+    }
+    if resource.status != ResourceStatus::Available {
+        return Err(error!(ResourceError::ResourceUnavailable));
+    }
+    if quantity == 0 || quantity > resource.quantity_remaining {
+        return Err(error!(ResourceError::InvalidQuantity));
+    }
+    if inspection_period < 0 {
+        return Err(error!(ExchangeError::InvalidExpiry));
+    }
+
+    let amount = resource.total_price(quantity)?;
+
+    token::transfer(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            token::Transfer {
+                from: ctx.accounts.buyer_token_account.to_account_info(),
+                to: ctx.accounts.vault.to_account_info(),
+                authority: ctx.accounts.buyer.to_account_info(),
+            },
+        ),
+        amount,
+    )?;
+
+    escrow.resource = resource.key();
+    escrow.buyer = ctx.accounts.buyer.key();
+    escrow.provider = resource.provider;
+    escrow.payment_mint = resource.payment_mint;
+    escrow.index = resource.purchase_count;
+    escrow.quantity = quantity;
+    escrow.amount = amount;
+    escrow.created_at = now;
+    escrow.release_after = now.checked_add(inspection_period)
+        .ok_or(ExchangeError::InvalidExpiry)?;
+    escrow.status = EscrowStatus::Funded;
+    escrow.bump = *ctx.bumps.get("escrow").unwrap();
+
+    resource.open_commitment()?;
+    resource.quantity_remaining -= quantity;
+    resource.purchase_count = resource.purchase_count.checked_add(1)
+        .ok_or(ResourceError::PriceOverflow)?;
+    if resource.quantity_remaining == 0 {
+        resource.status.ensure_transition(&ResourceStatus::Exchanged)?;
+        resource.status = ResourceStatus::Exchanged;
+    }
+
+    emit!(EscrowFunded {
+        escrow: escrow.key(),
+        resource: escrow.resource,
+        buyer: escrow.buyer,
+        amount,
+        release_after: escrow.release_after,
+    });
+
+    Ok(())
+}
+
+// The buyer may release at any time; the provider once the inspection
+// period has passed without a dispute.
+pub fn handle_release_escrow(ctx: Context<ReleaseEscrow>) -> Result<()> {
+    let escrow = &ctx.accounts.escrow;
+    let authority = ctx.accounts.authority.key();
+
+    if escrow.status != EscrowStatus::Funded {
+        return Err(error!(ExchangeError::InvalidEscrowStatus));
+    }
+    if authority == escrow.provider {
+        if Clock::get()?.unix_timestamp < escrow.release_after {
+            return Err(error!(ExchangeError::EscrowLocked));
+        }
+    } else if authority != escrow.buyer {
+        return Err(error!(ExchangeError::UnauthorizedParty));
+    }
+
+    let token_program = ctx.accounts.token_program.to_account_info();
+    let vault = ctx.accounts.vault.to_account_info();
+    let exempt = ctx.accounts.platform.platform_config
+        .is_fee_exempt(&ctx.accounts.access_control, &escrow.provider);
+    pay_provider(
+        &ctx.accounts.platform.platform_config,
+        exempt,
+        &token_program,
+        &vault,
+        &ctx.accounts.provider_token_account.to_account_info(),
+        &ctx.accounts.treasury_token_account.to_account_info(),
+        escrow,
+        escrow.amount,
+    )?;
+    close_escrow_vault(&token_program, &vault, &ctx.accounts.buyer, escrow)?;
+
+    record_purchase(
+        &mut ctx.accounts.purchase,
+        escrow,
+        escrow.amount,
+        *ctx.bumps.get("purchase").unwrap(),
+    )?;
+
+    let escrow = &mut ctx.accounts.escrow;
+    escrow.status = EscrowStatus::Released;
+    ctx.accounts.resource.settle_commitment();
+
+    emit!(EscrowReleased {
+        escrow: escrow.key(),
+        released_by: authority,
+        amount: escrow.amount,
+    });
+
+    Ok(())
+}
+
+// Either party freezes a funded escrow pending arbitration
+pub fn handle_open_dispute(
+    ctx: Context<OpenDispute>,
+    evidence_uri: String,
+    evidence_hash: [u8; 32],
+) -> Result<()> {
+    let escrow = &mut ctx.accounts.escrow;
+    let dispute = &mut ctx.accounts.dispute;
+    let authority = ctx.accounts.authority.key();
+    let now = Clock::get()?.unix_timestamp;
+
+    if authority != escrow.buyer && authority != escrow.provider {
+        return Err(error!(ExchangeError::UnauthorizedParty));
+    }
+    if escrow.status != EscrowStatus::Funded {
+        return Err(error!(ExchangeError::InvalidEscrowStatus));
+    }
+    if evidence_uri.len() > MAX_EVIDENCE_URI_LEN {
+        return Err(error!(ExchangeError::EvidenceTooLong));
+    }
+
+    escrow.status = EscrowStatus::Disputed;
+
+    dispute.escrow = escrow.key();
+    dispute.opened_by = authority;
+    dispute.claimant_evidence = Evidence { uri: evidence_uri, hash: evidence_hash };
+    dispute.respondent_evidence = None;
+    dispute.response_deadline = now + DISPUTE_RESPONSE_PERIOD;
+    dispute.ruling_deadline = dispute.response_deadline + DISPUTE_RULING_PERIOD;
+    dispute.status = DisputeStatus::Open;
+    dispute.ruling = None;
+    dispute.resolved_by = None;
+    dispute.bump = *ctx.bumps.get("dispute").unwrap();
+
+    emit!(DisputeOpened {
+        dispute: dispute.key(),
+        escrow: dispute.escrow,
+        opened_by: authority,
+        evidence_hash,
+        response_deadline: dispute.response_deadline,
+    });
+
+    Ok(())
+}
+
+// The other party answers with their own evidence before the deadline
+pub fn handle_respond_dispute(
+    ctx: Context<RespondDispute>,
+    evidence_uri: String,
+    evidence_hash: [u8; 32],
+) -> Result<()> {
+    let escrow = &ctx.accounts.escrow;
+    let dispute = &mut ctx.accounts.dispute;
+    let authority = ctx.accounts.authority.key();
+    let now = Clock::get()?.unix_timestamp;
+
+    let respondent = if dispute.opened_by == escrow.buyer {
+        escrow.provider
+    } else {
+        escrow.buyer
+    };
+    if authority != respondent {
+        return Err(error!(ExchangeError::UnauthorizedParty));
+    }
+    if dispute.status != DisputeStatus::Open {
+        return Err(error!(ExchangeError::InvalidDisputeStatus));
+    }
+    if now >= dispute.response_deadline {
+        return Err(error!(ExchangeError::DisputeDeadlinePassed));
+    }
+    if evidence_uri.len() > MAX_EVIDENCE_URI_LEN {
+        return Err(error!(ExchangeError::EvidenceTooLong));
+    }
+
+    dispute.respondent_evidence = Some(Evidence { uri: evidence_uri, hash: evidence_hash });
+    dispute.status = DisputeStatus::Responded;
+    dispute.ruling_deadline = now + DISPUTE_RULING_PERIOD;
+
+    emit!(DisputeResponded {
+        dispute: dispute.key(),
+        respondent: authority,
+        evidence_hash,
+        ruling_deadline: dispute.ruling_deadline,
+    });
+
+    Ok(())
+}
+
+// An arbiter or governance rules on an open dispute
+pub fn handle_resolve_dispute(ctx: Context<SettleDispute>, ruling: Ruling) -> Result<()> {
+    let authority = ctx.accounts.authority.key();
+    let access_control = &ctx.accounts.access_control;
+
+    if !access_control.has_role(&authority, &Role::Arbiter)
+        && !access_control.has_role(&authority, &Role::Governance)
+    {
+        return Err(error!(AccessControlError::UnauthorizedRole));
+    }
+    // Arbiters may not rule on their own escrows
+    let escrow = &ctx.accounts.escrow;
+    if authority == escrow.buyer || authority == escrow.provider {
+        return Err(error!(ExchangeError::UnauthorizedParty));
+    }
+    if ctx.accounts.dispute.status == DisputeStatus::Resolved {
+        return Err(error!(ExchangeError::InvalidDisputeStatus));
+    }
+
+    apply_ruling(ctx, ruling, Some(authority))
+}
+
+// Anyone applies the default outcome once a deadline has been missed
+pub fn handle_finalize_dispute(ctx: Context<SettleDispute>) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let ruling = ctx.accounts.dispute.default_ruling(&ctx.accounts.escrow, now)
+        .ok_or(ExchangeError::DisputeDeadlineNotReached)?;
+
+    apply_ruling(ctx, ruling, None)
+}
+
+fn apply_ruling(
+    ctx: Context<SettleDispute>,
+    ruling: Ruling,
+    resolved_by: Option<Pubkey>,
+) -> Result<()> {
+    let escrow = &ctx.accounts.escrow;
+    let (buyer_amount, provider_amount) = ruling.apportion(escrow.amount)?;
+
+    let token_program = ctx.accounts.token_program.to_account_info();
+    let vault = ctx.accounts.vault.to_account_info();
+    if buyer_amount > 0 {
+        escrow_transfer(
+            &token_program,
+            &vault,
+            &ctx.accounts.buyer_token_account.to_account_info(),
+            escrow,
+            buyer_amount,
+        )?;
+    }
+    if provider_amount > 0 {
+        let exempt = ctx.accounts.platform_config
+            .is_fee_exempt(&ctx.accounts.access_control, &escrow.provider);
+        pay_provider(
+            &ctx.accounts.platform_config,
+            exempt,
+            &token_program,
+            &vault,
+            &ctx.accounts.provider_token_account.to_account_info(),
+            &ctx.accounts.treasury_token_account.to_account_info(),
+            escrow,
+            provider_amount,
+        )?;
+    }
+    close_escrow_vault(&token_program, &vault, &ctx.accounts.buyer, escrow)?;
+
+    // A refund returns the units to the resource; otherwise the purchase
+    // stands at the amount the provider received
+    let resource = &mut ctx.accounts.resource;
+    if ruling == Ruling::Refund {
+        resource.restock(escrow.quantity)?;
+    } else {
+        let purchase = ctx.accounts.purchase.as_mut()
+            .ok_or(ExchangeError::MissingPurchaseReceipt)?;
+        record_purchase(purchase, escrow, provider_amount, *ctx.bumps.get("purchase").unwrap())?;
+    }
+    resource.settle_commitment();
+
+    let escrow = &mut ctx.accounts.escrow;
+    escrow.status = match ruling {
+        Ruling::Release => EscrowStatus::Released,
+        Ruling::Refund => EscrowStatus::Refunded,
+        Ruling::Split { .. } => EscrowStatus::Split,
+    };
+
+    let dispute = &mut ctx.accounts.dispute;
+    dispute.status = DisputeStatus::Resolved;
+    dispute.ruling = Some(ruling.clone());
+    dispute.resolved_by = resolved_by;
+
+    emit!(DisputeResolved {
+        dispute: dispute.key(),
+        escrow: escrow.key(),
+        ruling,
+        buyer_amount,
+        provider_amount,
+        resolved_by,
+    });
+
+    Ok(())
+}
+
+// Write the purchase receipt for a settled escrow
+fn record_purchase(
+    purchase: &mut Purchase,
+    escrow: &Escrow,
+    total_price: u64,
+    bump: u8,
+) -> Result<()> {
+    purchase.resource = escrow.resource;
+    purchase.buyer = escrow.buyer;
+    purchase.payment_mint = escrow.payment_mint;
+    purchase.quantity = escrow.quantity;
+    purchase.unit_price = escrow.amount / escrow.quantity;
+    purchase.total_price = total_price;
+    purchase.purchased_at = Clock::get()?.unix_timestamp;
+    purchase.bump = bump;
+    Ok(())
+}
+
+fn escrow_transfer<'info>(
+    token_program: &AccountInfo<'info>,
+    vault: &AccountInfo<'info>,
+    to: &AccountInfo<'info>,
+    escrow: &Account<'info, Escrow>,
+    amount: u64,
+) -> Result<()> {
+    let index = escrow.index.to_le_bytes();
+    let seeds = &[
+        b"escrow",
+        escrow.resource.as_ref(),
+        escrow.buyer.as_ref(),
+        index.as_ref(),
+        &[escrow.bump],
+    ];
+
+    token::transfer(
+        CpiContext::new_with_signer(
+            token_program.clone(),
+            token::Transfer {
+                from: vault.clone(),
+                to: to.clone(),
+                authority: escrow.to_account_info(),
+            },
+            &[&seeds[..]],
+        ),
+        amount,
+    )
+}
+
+// Pay the provider's share of an escrow, less the platform fee
+#[allow(clippy::too_many_arguments)]
+fn pay_provider<'info>(
+    platform_config: &PlatformConfig,
+    exempt: bool,
+    token_program: &AccountInfo<'info>,
+    vault: &AccountInfo<'info>,
+    provider_account: &AccountInfo<'info>,
+    treasury_account: &AccountInfo<'info>,
+    escrow: &Account<'info, Escrow>,
+    amount: u64,
+) -> Result<()> {
+    let index = escrow.index.to_le_bytes();
+    let seeds = &[
+        b"escrow",
+        escrow.resource.as_ref(),
+        escrow.buyer.as_ref(),
+        index.as_ref(),
+        &[escrow.bump],
+    ];
+
+    let fee = pay_with_fee(
+        platform_config,
+        exempt,
+        token_program,
+        vault,
+        provider_account,
+        treasury_account,
+        &escrow.to_account_info(),
+        &[&seeds[..]],
+        amount,
+    )?;
+    if fee > 0 {
+        emit!(FeeCollected {
+            mint: escrow.payment_mint,
+            payee: escrow.provider,
+            gross: amount,
+            fee,
+        });
+    }
+
+    Ok(())
+}
+
+fn close_escrow_vault<'info>(
+    token_program: &AccountInfo<'info>,
+    vault: &AccountInfo<'info>,
+    buyer: &AccountInfo<'info>,
+    escrow: &Account<'info, Escrow>,
+) -> Result<()> {
+    let index = escrow.index.to_le_bytes();
+    let seeds = &[
+        b"escrow",
+        escrow.resource.as_ref(),
+        escrow.buyer.as_ref(),
+        index.as_ref(),
+        &[escrow.bump],
+    ];
+
+    token::close_account(CpiContext::new_with_signer(
+        token_program.clone(),
+        token::CloseAccount {
+            account: vault.clone(),
+            destination: buyer.clone(),
+            authority: escrow.to_account_info(),
+        },
+        &[&seeds[..]],
+    ))
+}
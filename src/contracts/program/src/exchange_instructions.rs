@@ -0,0 +1,1177 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Mint, Token, TokenAccount};
+use crate::access_control::AccessControl;
+use crate::exchange_standards::*;
+use crate::platform_instructions::*;
+use crate::platform_standards::{pay_with_fee, FeeCollected, PlatformError};
+use crate::token_standards::{TokenError, MAX_ROLE_LEN};
+use crate::*;
+
+#[derive(Accounts)]
+pub struct MakeBarterOffer<'info> {
+    #[account(mut)]
+    pub resource: Account<'info, Resource>,
+    #[account(has_one = creator)]
+    pub project: Account<'info, Project>,
+    #[account(
+        init,
+        payer = creator,
+        space = BarterOffer::LEN,
+        seeds = [b"barter", resource.key().as_ref(), project.key().as_ref()],
+        bump
+    )]
+    pub barter: Account<'info, BarterOffer>,
+    pub platform: ExchangeLive<'info>,
+    #[account(mut)]
+    pub creator: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptBarterOffer<'info> {
+    #[account(
+        mut,
+        has_one = resource,
+        has_one = project,
+        has_one = provider,
+        seeds = [b"barter", resource.key().as_ref(), project.key().as_ref()],
+        bump = barter.bump
+    )]
+    pub barter: Account<'info, BarterOffer>,
+    #[account(mut)]
+    pub resource: Account<'info, Resource>,
+    pub project: Account<'info, Project>,
+    #[account(init, payer = provider, space = Collaboration::LEN)]
+    pub collaboration: Account<'info, Collaboration>,
+    pub platform: ExchangeLive<'info>,
+    #[account(mut)]
+    pub provider: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SettleBarter<'info> {
+    #[account(
+        mut,
+        has_one = resource,
+        has_one = collaboration,
+        seeds = [b"barter", resource.key().as_ref(), barter.project.as_ref()],
+        bump = barter.bump
+    )]
+    pub barter: Account<'info, BarterOffer>,
+    #[account(mut)]
+    pub resource: Account<'info, Resource>,
+    pub collaboration: Account<'info, Collaboration>,
+    pub platform: ExchangeLive<'info>,
+}
+
+// Open while the exchange is paused, so escrowed barters can always be unwound
+#[derive(Accounts)]
+pub struct CancelBarter<'info> {
+    #[account(
+        mut,
+        close = buyer,
+        has_one = resource,
+        has_one = buyer,
+        seeds = [b"barter", resource.key().as_ref(), barter.project.as_ref()],
+        bump = barter.bump
+    )]
+    pub barter: Account<'info, BarterOffer>,
+    #[account(mut)]
+    pub resource: Account<'info, Resource>,
+    // Required once the offer has been accepted
+    #[account(mut, address = barter.collaboration)]
+    pub collaboration: Option<Account<'info, Collaboration>>,
+    /// CHECK: Offer creator receiving the rent refund
+    #[account(mut)]
+    pub buyer: AccountInfo<'info>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct OpenRevenueLedger<'info> {
+    #[account(has_one = creator)]
+    pub project: Account<'info, Project>,
+    pub mint: Account<'info, Mint>,
+    #[account(
+        init,
+        payer = creator,
+        space = RevenueLedger::LEN,
+        seeds = [b"revenue_ledger", project.key().as_ref(), mint.key().as_ref()],
+        bump
+    )]
+    pub ledger: Account<'info, RevenueLedger>,
+    pub platform: ExchangeLive<'info>,
+    #[account(mut)]
+    pub creator: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ExchangeFutureBenefit<'info> {
+    #[account(mut, has_one = provider)]
+    pub resource: Account<'info, Resource>,
+    #[account(has_one = creator)]
+    pub project: Account<'info, Project>,
+    #[account(
+        mut,
+        has_one = project,
+        seeds = [b"revenue_ledger", project.key().as_ref(), ledger.mint.as_ref()],
+        bump = ledger.bump
+    )]
+    pub ledger: Account<'info, RevenueLedger>,
+    #[account(
+        init,
+        payer = creator,
+        space = FutureBenefitAgreement::LEN,
+        seeds = [b"future_benefit", resource.key().as_ref(), project.key().as_ref()],
+        bump
+    )]
+    pub agreement: Account<'info, FutureBenefitAgreement>,
+    pub platform: ExchangeLive<'info>,
+    #[account(mut)]
+    pub creator: Signer<'info>,
+    pub provider: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+// Remaining accounts: one (agreement, provider token account) pair per
+// outstanding agreement, ordered by agreement key.
+#[derive(Accounts)]
+pub struct DistributeProjectRevenue<'info> {
+    #[account(has_one = creator)]
+    pub project: Account<'info, Project>,
+    #[account(
+        mut,
+        has_one = project,
+        has_one = mint,
+        seeds = [b"revenue_ledger", project.key().as_ref(), mint.key().as_ref()],
+        bump = ledger.bump
+    )]
+    pub ledger: Account<'info, RevenueLedger>,
+    pub mint: Account<'info, Mint>,
+    #[account(mut, token::mint = mint, token::authority = creator)]
+    pub creator_token_account: Account<'info, TokenAccount>,
+    pub platform: ExchangeLive<'info>,
+    pub creator: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct MakeOffer<'info> {
+    #[account(mut)]
+    pub resource: Account<'info, Resource>,
+    #[account(
+        init,
+        payer = buyer,
+        space = Offer::LEN,
+        seeds = [b"offer", resource.key().as_ref(), buyer.key().as_ref()],
+        bump
+    )]
+    pub offer: Account<'info, Offer>,
+    #[account(address = resource.payment_mint @ ResourceError::WrongPaymentMint)]
+    pub payment_mint: Account<'info, Mint>,
+    #[account(
+        init,
+        payer = buyer,
+        seeds = [b"offer_vault", offer.key().as_ref()],
+        bump,
+        token::mint = payment_mint,
+        token::authority = offer
+    )]
+    pub vault: Account<'info, TokenAccount>,
+    #[account(mut, token::mint = payment_mint, token::authority = buyer)]
+    pub buyer_token_account: Account<'info, TokenAccount>,
+    pub platform: ExchangeLive<'info>,
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct CounterOffer<'info> {
+    #[account(
+        mut,
+        has_one = provider,
+        seeds = [b"offer", offer.resource.as_ref(), offer.buyer.as_ref()],
+        bump = offer.bump
+    )]
+    pub offer: Account<'info, Offer>,
+    pub platform: ExchangeLive<'info>,
+    pub provider: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptOffer<'info> {
+    #[account(
+        mut,
+        close = buyer,
+        has_one = resource,
+        has_one = buyer,
+        has_one = provider,
+        seeds = [b"offer", resource.key().as_ref(), buyer.key().as_ref()],
+        bump = offer.bump
+    )]
+    pub offer: Account<'info, Offer>,
+    #[account(mut)]
+    pub resource: Account<'info, Resource>,
+    #[account(
+        init,
+        payer = authority,
+        space = Purchase::LEN,
+        seeds = [
+            b"purchase",
+            resource.key().as_ref(),
+            buyer.key().as_ref(),
+            &resource.purchase_count.to_le_bytes(),
+        ],
+        bump
+    )]
+    pub purchase: Account<'info, Purchase>,
+    #[account(address = offer.payment_mint)]
+    pub payment_mint: Account<'info, Mint>,
+    #[account(mut, seeds = [b"offer_vault", offer.key().as_ref()], bump)]
+    pub vault: Account<'info, TokenAccount>,
+    #[account(mut, token::mint = payment_mint, token::authority = buyer)]
+    pub buyer_token_account: Account<'info, TokenAccount>,
+    #[account(mut, token::mint = payment_mint, token::authority = provider)]
+    pub provider_token_account: Account<'info, TokenAccount>,
+    pub platform: ExchangeLive<'info>,
+    #[account(seeds = [b"access_control"], bump)]
+    pub access_control: Account<'info, AccessControl>,
+    #[account(
+        mut,
+        token::mint = payment_mint,
+        constraint = platform.platform_config.is_treasury(&payment_mint.key(), &treasury_token_account.key())
+            @ PlatformError::UnknownTreasury
+    )]
+    pub treasury_token_account: Account<'info, TokenAccount>,
+    /// CHECK: Offer buyer, receives refunds and rent
+    #[account(mut)]
+    pub buyer: AccountInfo<'info>,
+    /// CHECK: Resource provider
+    pub provider: AccountInfo<'info>,
+    // Provider accepting an open offer, or buyer accepting a counter-offer
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+// Open while the exchange is paused, so escrowed offers can always be returned
+#[derive(Accounts)]
+pub struct CloseOffer<'info> {
+    #[account(
+        mut,
+        close = buyer,
+        has_one = buyer,
+        seeds = [b"offer", offer.resource.as_ref(), buyer.key().as_ref()],
+        bump = offer.bump
+    )]
+    pub offer: Account<'info, Offer>,
+    #[account(mut, address = offer.resource)]
+    pub resource: Account<'info, Resource>,
+    #[account(mut, seeds = [b"offer_vault", offer.key().as_ref()], bump)]
+    pub vault: Account<'info, TokenAccount>,
+    #[account(mut, token::mint = offer.payment_mint, token::authority = buyer)]
+    pub buyer_token_account: Account<'info, TokenAccount>,
+    /// CHECK: Offer buyer, receives refunds and rent
+    #[account(mut)]
+    pub buyer: AccountInfo<'info>,
+    pub authority: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct CreateAuction<'info> {
+    #[account(mut, has_one = provider)]
+    pub resource: Account<'info, Resource>,
+    #[account(
+        init,
+        payer = provider,
+        space = Auction::LEN,
+        seeds = [b"auction", resource.key().as_ref()],
+        bump
+    )]
+    pub auction: Account<'info, Auction>,
+    #[account(address = resource.payment_mint @ ResourceError::WrongPaymentMint)]
+    pub payment_mint: Account<'info, Mint>,
+    #[account(
+        init,
+        payer = provider,
+        seeds = [b"auction_vault", auction.key().as_ref()],
+        bump,
+        token::mint = payment_mint,
+        token::authority = auction
+    )]
+    pub vault: Account<'info, TokenAccount>,
+    pub platform: ExchangeLive<'info>,
+    #[account(mut)]
+    pub provider: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct PlaceBid<'info> {
+    #[account(
+        mut,
+        seeds = [b"auction", auction.resource.as_ref()],
+        bump = auction.bump
+    )]
+    pub auction: Account<'info, Auction>,
+    #[account(mut, seeds = [b"auction_vault", auction.key().as_ref()], bump)]
+    pub vault: Account<'info, TokenAccount>,
+    #[account(mut, token::mint = auction.payment_mint, token::authority = bidder)]
+    pub bidder_token_account: Account<'info, TokenAccount>,
+    // Refund destination for the bidder being outbid
+    #[account(mut, token::mint = auction.payment_mint)]
+    pub previous_bidder_token_account: Option<Account<'info, TokenAccount>>,
+    pub platform: ExchangeLive<'info>,
+    pub bidder: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct SettleAuction<'info> {
+    #[account(
+        mut,
+        close = provider,
+        has_one = resource,
+        has_one = provider,
+        seeds = [b"auction", resource.key().as_ref()],
+        bump = auction.bump
+    )]
+    pub auction: Account<'info, Auction>,
+    #[account(mut)]
+    pub resource: Account<'info, Resource>,
+    #[account(mut, seeds = [b"auction_vault", auction.key().as_ref()], bump)]
+    pub vault: Account<'info, TokenAccount>,
+    #[account(mut, token::mint = auction.payment_mint, token::authority = provider)]
+    pub provider_token_account: Account<'info, TokenAccount>,
+    pub platform: ExchangeLive<'info>,
+    #[account(seeds = [b"access_control"], bump)]
+    pub access_control: Account<'info, AccessControl>,
+    #[account(
+        mut,
+        token::mint = auction.payment_mint,
+        constraint = platform.platform_config.is_treasury(&auction.payment_mint, &treasury_token_account.key())
+            @ PlatformError::UnknownTreasury
+    )]
+    pub treasury_token_account: Account<'info, TokenAccount>,
+    /// CHECK: Auction provider, receives proceeds and rent
+    #[account(mut)]
+    pub provider: AccountInfo<'info>,
+    // Anyone may settle once the auction has ended
+    pub authority: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+// Exchange instruction handlers
+pub fn handle_make_barter_offer(
+    ctx: Context<MakeBarterOffer>,
+    role: String,
+    token_allocation: u64,
+    quantity: u64,
+) -> Result<()> {
+    let resource = &mut ctx.accounts.resource;
+    let barter = &mut ctx.accounts.barter;
+
+    if resource.exchange_type != ExchangeType::Collaboration {
+        return Err(error!(ExchangeError::WrongExchangeType));
+    }
+    if resource.status != ResourceStatus::Available {
+        return Err(error!(ResourceError::ResourceUnavailable));
+    }
+    if quantity == 0 || quantity > resource.quantity_remaining {
+        return Err(error!(ResourceError::InvalidQuantity));
+    }
+    if role.len() > MAX_ROLE_LEN {
+        return Err(error!(TokenError::MetadataTooLong));
+    }
+
+    barter.resource = resource.key();
+    barter.project = ctx.accounts.project.key();
+    barter.buyer = ctx.accounts.creator.key();
+    barter.provider = resource.provider;
+    barter.role = role;
+    barter.token_allocation = token_allocation;
+    barter.quantity = quantity;
+    barter.collaboration = Pubkey::default();
+    barter.status = BarterStatus::Offered;
+    barter.created_at = Clock::get()?.unix_timestamp;
+    barter.bump = *ctx.bumps.get("barter").unwrap();
+    resource.open_commitment()?;
+
+    emit!(BarterOffered {
+        barter: barter.key(),
+        resource: barter.resource,
+        project: barter.project,
+        token_allocation,
+        quantity,
+    });
+
+    Ok(())
+}
+
+pub fn handle_accept_barter_offer(ctx: Context<AcceptBarterOffer>) -> Result<()> {
+    let barter = &mut ctx.accounts.barter;
+    let resource = &mut ctx.accounts.resource;
+    let collaboration = &mut ctx.accounts.collaboration;
+
+    if barter.status != BarterStatus::Offered {
+        return Err(error!(ExchangeError::InvalidOfferStatus));
+    }
+    if barter.quantity > resource.quantity_remaining {
+        return Err(error!(ResourceError::InvalidQuantity));
+    }
+
+    // Hold the resource until the project creator approves the collaboration
+    resource.status.ensure_transition(&ResourceStatus::Reserved)?;
+    resource.status = ResourceStatus::Reserved;
+
+    collaboration.project = barter.project;
+    collaboration.collaborator = barter.provider;
+    collaboration.role = barter.role.clone();
+    collaboration.token_allocation = barter.token_allocation;
+    collaboration.status = CollaborationStatus::Pending;
+    collaboration.joined_at = Clock::get()?.unix_timestamp;
+
+    barter.collaboration = collaboration.key();
+    barter.status = BarterStatus::Accepted;
+
+    emit!(BarterAccepted {
+        barter: barter.key(),
+        collaboration: barter.collaboration,
+    });
+
+    Ok(())
+}
+
+pub fn handle_settle_barter(ctx: Context<SettleBarter>) -> Result<()> {
+    let barter = &mut ctx.accounts.barter;
+    let resource = &mut ctx.accounts.resource;
+
+    if barter.status != BarterStatus::Accepted {
+        return Err(error!(ExchangeError::InvalidOfferStatus));
+    }
+    if ctx.accounts.collaboration.status != CollaborationStatus::Active {
+        return Err(error!(ExchangeError::CollaborationNotActive));
+    }
+
+    resource.quantity_remaining = resource.quantity_remaining.checked_sub(barter.quantity)
+        .ok_or(ResourceError::InvalidQuantity)?;
+    resource.status = if resource.quantity_remaining == 0 {
+        ResourceStatus::Exchanged
+    } else {
+        ResourceStatus::Available
+    };
+    resource.settle_commitment();
+    barter.status = BarterStatus::Settled;
+
+    emit!(BarterSettled {
+        barter: barter.key(),
+        resource: resource.key(),
+        collaboration: barter.collaboration,
+    });
+
+    Ok(())
+}
+
+pub fn handle_cancel_barter(ctx: Context<CancelBarter>) -> Result<()> {
+    let barter = &mut ctx.accounts.barter;
+    let authority = ctx.accounts.authority.key();
+
+    if authority != barter.buyer && authority != barter.provider {
+        return Err(error!(ExchangeError::UnauthorizedParty));
+    }
+
+    match barter.status {
+        BarterStatus::Offered => {}
+        BarterStatus::Accepted => {
+            // Only unwind collaborations the creator has not yet approved
+            let collaboration = ctx.accounts.collaboration.as_mut()
+                .ok_or(ExchangeError::InvalidOfferStatus)?;
+            if collaboration.status != CollaborationStatus::Pending {
+                return Err(error!(ExchangeError::InvalidOfferStatus));
+            }
+            collaboration.status = CollaborationStatus::Ended;
+            let resource = &mut ctx.accounts.resource;
+            resource.status.ensure_transition(&ResourceStatus::Available)?;
+            resource.status = ResourceStatus::Available;
+        }
+        _ => return Err(error!(ExchangeError::InvalidOfferStatus)),
+    }
+    ctx.accounts.resource.settle_commitment();
+
+    emit!(BarterCancelled {
+        barter: barter.key(),
+        cancelled_by: authority,
+    });
+
+    Ok(())
+}
+
+pub fn handle_open_revenue_ledger(ctx: Context<OpenRevenueLedger>) -> Result<()> {
+    let ledger = &mut ctx.accounts.ledger;
+
+    ledger.project = ctx.accounts.project.key();
+    ledger.mint = ctx.accounts.mint.key();
+    ledger.active_agreements = 0;
+    ledger.total_share_bps = 0;
+    ledger.total_distributed = 0;
+    ledger.bump = *ctx.bumps.get("ledger").unwrap();
+
+    Ok(())
+}
+
+// Pay for a resource with a share of the project's future revenue. Both the
+// project creator and the provider sign.
+pub fn handle_exchange_future_benefit(
+    ctx: Context<ExchangeFutureBenefit>,
+    share_bps: u16,
+    cap: u64,
+    expires_at: i64,
+    quantity: u64,
+) -> Result<()> {
+    let resource = &mut ctx.accounts.resource;
+    let ledger = &mut ctx.accounts.ledger;
+    let agreement = &mut ctx.accounts.agreement;
+    let now = Clock::get()?.unix_timestamp;
+
+    if resource.exchange_type != ExchangeType::FutureBenefit {
+        return Err(error!(ExchangeError::WrongExchangeType));
+    }
+    if resource.status != ResourceStatus::Available {
+        return Err(error!(ResourceError::ResourceUnavailable));
+    }
+    if quantity == 0 || quantity > resource.quantity_remaining {
+        return Err(error!(ResourceError::InvalidQuantity));
+    }
+    if share_bps == 0 || cap == 0 {
+        return Err(error!(ExchangeError::InvalidShare));
+    }
+    if expires_at <= now {
+        return Err(error!(ExchangeError::InvalidExpiry));
+    }
+
+    let total_share_bps = ledger.total_share_bps.checked_add(share_bps)
+        .filter(|bps| *bps as u64 <= BPS_DENOMINATOR)
+        .ok_or(ExchangeError::ShareCapExceeded)?;
+
+    agreement.resource = resource.key();
+    agreement.project = ctx.accounts.project.key();
+    agreement.provider = resource.provider;
+    agreement.mint = ledger.mint;
+    agreement.share_bps = share_bps;
+    agreement.cap = cap;
+    agreement.claimed = 0;
+    agreement.expires_at = expires_at;
+    agreement.status = AgreementStatus::Active;
+    agreement.created_at = now;
+    agreement.bump = *ctx.bumps.get("agreement").unwrap();
+
+    ledger.total_share_bps = total_share_bps;
+    ledger.active_agreements = ledger.active_agreements.checked_add(1)
+        .ok_or(ExchangeError::ShareCapExceeded)?;
+
+    resource.quantity_remaining -= quantity;
+    if resource.quantity_remaining == 0 {
+        resource.status = ResourceStatus::Exchanged;
+    }
+
+    emit!(FutureBenefitCreated {
+        agreement: agreement.key(),
+        resource: agreement.resource,
+        project: agreement.project,
+        share_bps,
+        cap,
+        expires_at,
+    });
+
+    Ok(())
+}
+
+// Deposit revenue for a project, paying every outstanding agreement its
+// share. Expired agreements are retired without payment.
+pub fn handle_distribute_project_revenue<'info>(
+    ctx: Context<'_, '_, '_, 'info, DistributeProjectRevenue<'info>>,
+    amount: u64,
+) -> Result<()> {
+    let ledger = &mut ctx.accounts.ledger;
+    let now = Clock::get()?.unix_timestamp;
+
+    if !ctx.remaining_accounts.len().is_multiple_of(2)
+        || ctx.remaining_accounts.len() / 2 != ledger.active_agreements as usize
+    {
+        return Err(error!(ExchangeError::AgreementSetMismatch));
+    }
+
+    let mut paid_to_agreements: u64 = 0;
+    let mut previous_key: Option<Pubkey> = None;
+
+    for pair in ctx.remaining_accounts.chunks(2) {
+        let agreement_info = &pair[0];
+        let provider_account_info = &pair[1];
+
+        // Strictly increasing keys rule out duplicates
+        if previous_key.is_some_and(|key| key >= agreement_info.key()) {
+            return Err(error!(ExchangeError::AgreementSetMismatch));
+        }
+        previous_key = Some(agreement_info.key());
+
+        let mut agreement = Account::<FutureBenefitAgreement>::try_from(agreement_info)?;
+        if agreement.project != ledger.project
+            || agreement.mint != ledger.mint
+            || agreement.status != AgreementStatus::Active
+        {
+            return Err(error!(ExchangeError::InvalidAgreement));
+        }
+
+        if now >= agreement.expires_at {
+            agreement.status = AgreementStatus::Expired;
+            ledger.release(&agreement);
+            agreement.exit(&crate::ID)?;
+            continue;
+        }
+
+        let payout = agreement.payout_for(amount);
+        if payout > 0 {
+            let provider_account = Account::<TokenAccount>::try_from(provider_account_info)?;
+            if provider_account.owner != agreement.provider || provider_account.mint != ledger.mint {
+                return Err(error!(ExchangeError::InvalidAgreement));
+            }
+
+            token::transfer(
+                CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    token::Transfer {
+                        from: ctx.accounts.creator_token_account.to_account_info(),
+                        to: provider_account_info.clone(),
+                        authority: ctx.accounts.creator.to_account_info(),
+                    },
+                ),
+                payout,
+            )?;
+
+            agreement.claimed = agreement.claimed.checked_add(payout)
+                .ok_or(ResourceError::PriceOverflow)?;
+            paid_to_agreements = paid_to_agreements.checked_add(payout)
+                .ok_or(ResourceError::PriceOverflow)?;
+        }
+
+        if agreement.claimed >= agreement.cap {
+            agreement.status = AgreementStatus::Fulfilled;
+            ledger.release(&agreement);
+        }
+        agreement.exit(&crate::ID)?;
+    }
+
+    ledger.total_distributed = ledger.total_distributed.checked_add(paid_to_agreements)
+        .ok_or(ResourceError::PriceOverflow)?;
+
+    emit!(RevenueDistributed {
+        project: ledger.project,
+        mint: ledger.mint,
+        amount,
+        paid_to_agreements,
+    });
+
+    Ok(())
+}
+
+pub fn handle_make_offer(
+    ctx: Context<MakeOffer>,
+    price: u64,
+    quantity: u64,
+    expires_at: i64,
+) -> Result<()> {
+    let resource = &mut ctx.accounts.resource;
+    let offer = &mut ctx.accounts.offer;
+    let now = Clock::get()?.unix_timestamp;
+
+    if resource.exchange_type != ExchangeType::Token {
+        return Err(error!(ExchangeError::WrongExchangeType));
+    }
+    if resource.status != ResourceStatus::Available {
+        return Err(error!(ResourceError::ResourceUnavailable));
+    }
+    if quantity == 0 || quantity > resource.quantity_remaining {
+        return Err(error!(ResourceError::InvalidQuantity));
+    }
+    if expires_at <= now {
+        return Err(error!(ExchangeError::InvalidExpiry));
+    }
+
+    offer.resource = resource.key();
+    offer.buyer = ctx.accounts.buyer.key();
+    offer.provider = resource.provider;
+    offer.payment_mint = resource.payment_mint;
+    offer.price = price;
+    offer.quantity = quantity;
+    offer.expires_at = expires_at;
+    offer.status = OfferStatus::Open;
+    offer.created_at = now;
+    offer.bump = *ctx.bumps.get("offer").unwrap();
+    offer.escrowed = offer.total_price()?;
+    resource.open_commitment()?;
+
+    // Escrow the offered funds
+    token::transfer(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            token::Transfer {
+                from: ctx.accounts.buyer_token_account.to_account_info(),
+                to: ctx.accounts.vault.to_account_info(),
+                authority: ctx.accounts.buyer.to_account_info(),
+            },
+        ),
+        offer.escrowed,
+    )?;
+
+    emit!(OfferMade {
+        offer: offer.key(),
+        resource: offer.resource,
+        buyer: offer.buyer,
+        price,
+        quantity,
+        expires_at,
+    });
+
+    Ok(())
+}
+
+// Provider proposes new terms. The escrow is adjusted when the buyer accepts.
+pub fn handle_counter_offer(
+    ctx: Context<CounterOffer>,
+    price: u64,
+    quantity: u64,
+    expires_at: i64,
+) -> Result<()> {
+    let offer = &mut ctx.accounts.offer;
+    let now = Clock::get()?.unix_timestamp;
+
+    if offer.status != OfferStatus::Open {
+        return Err(error!(ExchangeError::InvalidOfferStatus));
+    }
+    if quantity == 0 {
+        return Err(error!(ResourceError::InvalidQuantity));
+    }
+    if expires_at <= now {
+        return Err(error!(ExchangeError::InvalidExpiry));
+    }
+
+    offer.price = price;
+    offer.quantity = quantity;
+    offer.expires_at = expires_at;
+    offer.status = OfferStatus::Countered;
+    offer.total_price()?;
+
+    emit!(OfferCountered {
+        offer: offer.key(),
+        price,
+        quantity,
+        expires_at,
+    });
+
+    Ok(())
+}
+
+// Settle an offer at its current terms: the provider accepts an open offer,
+// the buyer accepts a counter-offer.
+pub fn handle_accept_offer(ctx: Context<AcceptOffer>) -> Result<()> {
+    let offer = &ctx.accounts.offer;
+    let resource = &mut ctx.accounts.resource;
+    let authority = ctx.accounts.authority.key();
+    let now = Clock::get()?.unix_timestamp;
+
+    let expected_signer = match offer.status {
+        OfferStatus::Open => offer.provider,
+        OfferStatus::Countered => offer.buyer,
+    };
+    if authority != expected_signer {
+        return Err(error!(ExchangeError::UnauthorizedParty));
+    }
+    if now >= offer.expires_at {
+        return Err(error!(ExchangeError::OfferExpired));
+    }
+    if resource.status != ResourceStatus::Available {
+        return Err(error!(ResourceError::ResourceUnavailable));
+    }
+    if offer.quantity > resource.quantity_remaining {
+        return Err(error!(ResourceError::InvalidQuantity));
+    }
+
+    let total_price = offer.total_price()?;
+
+    // A counter-offer above the escrow is topped up by the accepting buyer
+    if total_price > offer.escrowed {
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.buyer_token_account.to_account_info(),
+                    to: ctx.accounts.vault.to_account_info(),
+                    authority: ctx.accounts.authority.to_account_info(),
+                },
+            ),
+            total_price - offer.escrowed,
+        )?;
+    }
+
+    let resource_key = resource.key();
+    let buyer_key = offer.buyer;
+    let seeds = &[b"offer", resource_key.as_ref(), buyer_key.as_ref(), &[offer.bump]];
+    let signer = &[&seeds[..]];
+
+    // Pay the provider less the platform fee, then refund any excess escrow
+    let exempt = ctx.accounts.platform.platform_config
+        .is_fee_exempt(&ctx.accounts.access_control, &offer.provider);
+    let fee = pay_with_fee(
+        &ctx.accounts.platform.platform_config,
+        exempt,
+        &ctx.accounts.token_program.to_account_info(),
+        &ctx.accounts.vault.to_account_info(),
+        &ctx.accounts.provider_token_account.to_account_info(),
+        &ctx.accounts.treasury_token_account.to_account_info(),
+        &offer.to_account_info(),
+        signer,
+        total_price,
+    )?;
+    if fee > 0 {
+        emit!(FeeCollected {
+            mint: offer.payment_mint,
+            payee: offer.provider,
+            gross: total_price,
+            fee,
+        });
+    }
+
+    let refund = offer.escrowed.saturating_sub(total_price);
+    if refund > 0 {
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.vault.to_account_info(),
+                    to: ctx.accounts.buyer_token_account.to_account_info(),
+                    authority: offer.to_account_info(),
+                },
+                signer,
+            ),
+            refund,
+        )?;
+    }
+
+    token::close_account(CpiContext::new_with_signer(
+        ctx.accounts.token_program.to_account_info(),
+        token::CloseAccount {
+            account: ctx.accounts.vault.to_account_info(),
+            destination: ctx.accounts.buyer.to_account_info(),
+            authority: offer.to_account_info(),
+        },
+        signer,
+    ))?;
+
+    // Record the purchase and update the resource
+    let purchase = &mut ctx.accounts.purchase;
+    purchase.resource = resource_key;
+    purchase.buyer = buyer_key;
+    purchase.payment_mint = offer.payment_mint;
+    purchase.quantity = offer.quantity;
+    purchase.unit_price = offer.price;
+    purchase.total_price = total_price;
+    purchase.purchased_at = now;
+    purchase.bump = *ctx.bumps.get("purchase").unwrap();
+
+    resource.quantity_remaining -= offer.quantity;
+    resource.purchase_count = resource.purchase_count.checked_add(1)
+        .ok_or(ResourceError::PriceOverflow)?;
+    if resource.quantity_remaining == 0 {
+        resource.status = ResourceStatus::Exchanged;
+    }
+    resource.settle_commitment();
+
+    emit!(OfferAccepted {
+        offer: offer.key(),
+        resource: resource_key,
+        buyer: buyer_key,
+        price: offer.price,
+        quantity: offer.quantity,
+    });
+
+    Ok(())
+}
+
+// Buyer withdraws their offer
+pub fn handle_withdraw_offer(ctx: Context<CloseOffer>) -> Result<()> {
+    if ctx.accounts.authority.key() != ctx.accounts.offer.buyer {
+        return Err(error!(ExchangeError::UnauthorizedParty));
+    }
+    refund_offer(ctx)
+}
+
+// Provider rejects the offer
+pub fn handle_reject_offer(ctx: Context<CloseOffer>) -> Result<()> {
+    if ctx.accounts.authority.key() != ctx.accounts.offer.provider {
+        return Err(error!(ExchangeError::UnauthorizedParty));
+    }
+    refund_offer(ctx)
+}
+
+// Return escrowed funds and close the vault; the offer itself is closed by
+// the account constraint
+fn refund_offer(ctx: Context<CloseOffer>) -> Result<()> {
+    ctx.accounts.resource.settle_commitment();
+
+    let offer = &ctx.accounts.offer;
+    let refunded = ctx.accounts.vault.amount;
+
+    let seeds = &[b"offer", offer.resource.as_ref(), offer.buyer.as_ref(), &[offer.bump]];
+    let signer = &[&seeds[..]];
+
+    token::transfer(
+        CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            token::Transfer {
+                from: ctx.accounts.vault.to_account_info(),
+                to: ctx.accounts.buyer_token_account.to_account_info(),
+                authority: offer.to_account_info(),
+            },
+            signer,
+        ),
+        refunded,
+    )?;
+
+    token::close_account(CpiContext::new_with_signer(
+        ctx.accounts.token_program.to_account_info(),
+        token::CloseAccount {
+            account: ctx.accounts.vault.to_account_info(),
+            destination: ctx.accounts.buyer.to_account_info(),
+            authority: offer.to_account_info(),
+        },
+        signer,
+    ))?;
+
+    emit!(OfferClosed {
+        offer: offer.key(),
+        closed_by: ctx.accounts.authority.key(),
+        refunded,
+    });
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn handle_create_auction(
+    ctx: Context<CreateAuction>,
+    kind: AuctionKind,
+    start_price: u64,
+    reserve_price: u64,
+    min_increment: u64,
+    start_time: i64,
+    end_time: i64,
+    extension_window: i64,
+) -> Result<()> {
+    let resource = &mut ctx.accounts.resource;
+    let auction = &mut ctx.accounts.auction;
+
+    match resource.resource_type {
+        ResourceType::Material | ResourceType::Funding => {}
+        _ => return Err(error!(ExchangeError::NotAuctionable)),
+    }
+    if end_time <= start_time || end_time <= Clock::get()?.unix_timestamp || extension_window < 0 {
+        return Err(error!(ExchangeError::InvalidAuctionParams));
+    }
+    match kind {
+        AuctionKind::English if min_increment > 0 => {}
+        AuctionKind::Dutch if start_price >= reserve_price => {}
+        _ => return Err(error!(ExchangeError::InvalidAuctionParams)),
+    }
+
+    // The whole remaining stock is held back for the auction
+    resource.status.ensure_transition(&ResourceStatus::Reserved)?;
+    resource.status = ResourceStatus::Reserved;
+
+    auction.resource = resource.key();
+    auction.provider = resource.provider;
+    auction.payment_mint = resource.payment_mint;
+    auction.kind = kind.clone();
+    auction.quantity = resource.quantity_remaining;
+    auction.start_price = start_price;
+    auction.reserve_price = reserve_price;
+    auction.min_increment = min_increment;
+    auction.start_time = start_time;
+    auction.end_time = end_time;
+    auction.extension_window = extension_window;
+    auction.highest_bid = 0;
+    auction.highest_bidder = None;
+    auction.bump = *ctx.bumps.get("auction").unwrap();
+
+    emit!(AuctionCreated {
+        auction: auction.key(),
+        resource: auction.resource,
+        kind,
+        reserve_price,
+        start_time,
+        end_time,
+    });
+
+    Ok(())
+}
+
+// English auctions escrow `amount` and refund the previous high bidder.
+// Dutch auctions treat `amount` as the bidder's maximum, charge the current
+// price and end immediately.
+pub fn handle_place_bid(ctx: Context<PlaceBid>, amount: u64) -> Result<()> {
+    let auction = &mut ctx.accounts.auction;
+    let bidder = ctx.accounts.bidder.key();
+    let now = Clock::get()?.unix_timestamp;
+
+    if !auction.is_live(now) {
+        return Err(error!(ExchangeError::AuctionNotLive));
+    }
+
+    let charge = match auction.kind {
+        AuctionKind::English => {
+            if amount < auction.min_next_bid()? {
+                return Err(error!(ExchangeError::BidTooLow));
+            }
+            amount
+        }
+        AuctionKind::Dutch => {
+            let price = auction.current_price(now);
+            if amount < price {
+                return Err(error!(ExchangeError::BidTooLow));
+            }
+            price
+        }
+    };
+
+    token::transfer(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            token::Transfer {
+                from: ctx.accounts.bidder_token_account.to_account_info(),
+                to: ctx.accounts.vault.to_account_info(),
+                authority: ctx.accounts.bidder.to_account_info(),
+            },
+        ),
+        charge,
+    )?;
+
+    // Refund the bidder who has just been outbid
+    if let Some(previous_bidder) = auction.highest_bidder {
+        let refund_account = ctx.accounts.previous_bidder_token_account.as_ref()
+            .filter(|account| account.owner == previous_bidder)
+            .ok_or(ExchangeError::MissingRefundAccount)?;
+
+        let resource_key = auction.resource;
+        let seeds = &[b"auction", resource_key.as_ref(), &[auction.bump]];
+        let signer = &[&seeds[..]];
+
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.vault.to_account_info(),
+                    to: refund_account.to_account_info(),
+                    authority: auction.to_account_info(),
+                },
+                signer,
+            ),
+            auction.highest_bid,
+        )?;
+    }
+
+    auction.highest_bid = charge;
+    auction.highest_bidder = Some(bidder);
+
+    match auction.kind {
+        AuctionKind::English => {
+            // Anti-sniping: late bids extend the auction
+            if auction.end_time - now < auction.extension_window {
+                auction.end_time = now + auction.extension_window;
+            }
+        }
+        AuctionKind::Dutch => auction.end_time = now,
+    }
+
+    emit!(BidPlaced {
+        auction: auction.key(),
+        bidder,
+        amount: charge,
+        end_time: auction.end_time,
+    });
+
+    Ok(())
+}
+
+pub fn handle_settle_auction(ctx: Context<SettleAuction>) -> Result<()> {
+    let auction = &ctx.accounts.auction;
+    let resource = &mut ctx.accounts.resource;
+
+    if Clock::get()?.unix_timestamp < auction.end_time {
+        return Err(error!(ExchangeError::AuctionNotEnded));
+    }
+
+    let resource_key = resource.key();
+    let seeds = &[b"auction", resource_key.as_ref(), &[auction.bump]];
+    let signer = &[&seeds[..]];
+
+    if auction.highest_bidder.is_some() {
+        let exempt = ctx.accounts.platform.platform_config
+            .is_fee_exempt(&ctx.accounts.access_control, &auction.provider);
+        let fee = pay_with_fee(
+            &ctx.accounts.platform.platform_config,
+            exempt,
+            &ctx.accounts.token_program.to_account_info(),
+            &ctx.accounts.vault.to_account_info(),
+            &ctx.accounts.provider_token_account.to_account_info(),
+            &ctx.accounts.treasury_token_account.to_account_info(),
+            &auction.to_account_info(),
+            signer,
+            auction.highest_bid,
+        )?;
+        if fee > 0 {
+            emit!(FeeCollected {
+                mint: auction.payment_mint,
+                payee: auction.provider,
+                gross: auction.highest_bid,
+                fee,
+            });
+        }
+
+        resource.quantity_remaining = resource.quantity_remaining.saturating_sub(auction.quantity);
+        resource.status = if resource.quantity_remaining == 0 {
+            ResourceStatus::Exchanged
+        } else {
+            ResourceStatus::Available
+        };
+    } else {
+        // No bids: the resource goes back on the market
+        resource.status = ResourceStatus::Available;
+    }
+
+    token::close_account(CpiContext::new_with_signer(
+        ctx.accounts.token_program.to_account_info(),
+        token::CloseAccount {
+            account: ctx.accounts.vault.to_account_info(),
+            destination: ctx.accounts.provider.to_account_info(),
+            authority: auction.to_account_info(),
+        },
+        signer,
+    ))?;
+
+    emit!(AuctionSettled {
+        auction: auction.key(),
+        resource: resource_key,
+        winner: auction.highest_bidder,
+        amount: auction.highest_bid,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,664 @@
+use anchor_lang::prelude::*;
+use crate::token_standards::MAX_ROLE_LEN;
+
+// Status of a barter offer
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq)]
+pub enum BarterStatus {
+    Offered,
+    Accepted,
+    Settled,
+}
+
+// Offer to pay for a resource with a collaboration on the buyer's project.
+// PDA at ["barter", resource, project].
+#[account]
+pub struct BarterOffer {
+    pub resource: Pubkey,
+    pub project: Pubkey,
+    pub buyer: Pubkey,
+    pub provider: Pubkey,
+    pub role: String,
+    pub token_allocation: u64,
+    pub quantity: u64,
+    pub collaboration: Pubkey,
+    pub status: BarterStatus,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+impl BarterOffer {
+    pub const LEN: usize = 8 + 32 + 32 + 32 + 32 + (4 + MAX_ROLE_LEN) + 8 + 8 + 32 + 1 + 8 + 1;
+}
+
+#[event]
+pub struct BarterOffered {
+    pub barter: Pubkey,
+    pub resource: Pubkey,
+    pub project: Pubkey,
+    pub token_allocation: u64,
+    pub quantity: u64,
+}
+
+#[event]
+pub struct BarterAccepted {
+    pub barter: Pubkey,
+    pub collaboration: Pubkey,
+}
+
+#[event]
+pub struct BarterSettled {
+    pub barter: Pubkey,
+    pub resource: Pubkey,
+    pub collaboration: Pubkey,
+}
+
+#[event]
+pub struct BarterCancelled {
+    pub barter: Pubkey,
+    pub cancelled_by: Pubkey,
+}
+
+// Basis point denominator for revenue shares
+pub const BPS_DENOMINATOR: u64 = 10_000;
+
+// Status of a future benefit agreement
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq)]
+pub enum AgreementStatus {
+    Active,
+    Fulfilled,
+    Expired,
+}
+
+// Claim of a resource provider on a share of a project's future revenue in
+// one mint, up to a cap and until an expiry. PDA at ["future_benefit", resource, project].
+#[account]
+pub struct FutureBenefitAgreement {
+    pub resource: Pubkey,
+    pub project: Pubkey,
+    pub provider: Pubkey,
+    pub mint: Pubkey,
+    pub share_bps: u16,
+    pub cap: u64,
+    pub claimed: u64,
+    pub expires_at: i64,
+    pub status: AgreementStatus,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+impl FutureBenefitAgreement {
+    pub const LEN: usize = 8 + 32 + 32 + 32 + 32 + 2 + 8 + 8 + 8 + 1 + 8 + 1;
+
+    // Share of `amount` owed to the provider, rounded down and limited by the
+    // remaining cap
+    pub fn payout_for(&self, amount: u64) -> u64 {
+        let share = (amount as u128 * self.share_bps as u128 / BPS_DENOMINATOR as u128) as u64;
+        share.min(self.cap.saturating_sub(self.claimed))
+    }
+}
+
+// Per-project, per-mint register of outstanding future benefit agreements.
+// PDA at ["revenue_ledger", project, mint].
+#[account]
+pub struct RevenueLedger {
+    pub project: Pubkey,
+    pub mint: Pubkey,
+    pub active_agreements: u32,
+    pub total_share_bps: u16,
+    pub total_distributed: u64,
+    pub bump: u8,
+}
+
+impl RevenueLedger {
+    pub const LEN: usize = 8 + 32 + 32 + 4 + 2 + 8 + 1;
+
+    pub fn release(&mut self, agreement: &FutureBenefitAgreement) {
+        self.active_agreements = self.active_agreements.saturating_sub(1);
+        self.total_share_bps = self.total_share_bps.saturating_sub(agreement.share_bps);
+    }
+}
+
+#[event]
+pub struct FutureBenefitCreated {
+    pub agreement: Pubkey,
+    pub resource: Pubkey,
+    pub project: Pubkey,
+    pub share_bps: u16,
+    pub cap: u64,
+    pub expires_at: i64,
+}
+
+#[event]
+pub struct RevenueDistributed {
+    pub project: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub paid_to_agreements: u64,
+}
+
+// Which side of a negotiation must respond next
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq)]
+pub enum OfferStatus {
+    // Waiting on the provider
+    Open,
+    // Waiting on the buyer
+    Countered,
+}
+
+// Negotiated purchase of a resource with the buyer's funds held in escrow.
+// PDA at ["offer", resource, buyer]; the escrow vault is a token account at
+// ["offer_vault", offer] owned by the offer.
+#[account]
+pub struct Offer {
+    pub resource: Pubkey,
+    pub buyer: Pubkey,
+    pub provider: Pubkey,
+    pub payment_mint: Pubkey,
+    pub price: u64,
+    pub quantity: u64,
+    pub escrowed: u64,
+    pub expires_at: i64,
+    pub status: OfferStatus,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+impl Offer {
+    pub const LEN: usize = 8 + 32 + 32 + 32 + 32 + 8 + 8 + 8 + 8 + 1 + 8 + 1;
+
+    pub fn total_price(&self) -> Result<u64> {
+        self.price.checked_mul(self.quantity)
+            .ok_or(error!(crate::ResourceError::PriceOverflow))
+    }
+}
+
+#[event]
+pub struct OfferMade {
+    pub offer: Pubkey,
+    pub resource: Pubkey,
+    pub buyer: Pubkey,
+    pub price: u64,
+    pub quantity: u64,
+    pub expires_at: i64,
+}
+
+#[event]
+pub struct OfferCountered {
+    pub offer: Pubkey,
+    pub price: u64,
+    pub quantity: u64,
+    pub expires_at: i64,
+}
+
+#[event]
+pub struct OfferAccepted {
+    pub offer: Pubkey,
+    pub resource: Pubkey,
+    pub buyer: Pubkey,
+    pub price: u64,
+    pub quantity: u64,
+}
+
+#[event]
+pub struct OfferClosed {
+    pub offer: Pubkey,
+    pub closed_by: Pubkey,
+    pub refunded: u64,
+}
+
+// Price discovery mode of an auction
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq)]
+pub enum AuctionKind {
+    // Ascending bids, highest bid at end_time wins
+    English,
+    // Price falls linearly from start_price to reserve_price; first taker wins
+    Dutch,
+}
+
+// Auction of a resource's remaining units as one lot. PDA at
+// ["auction", resource]; bids are escrowed in ["auction_vault", auction].
+#[account]
+pub struct Auction {
+    pub resource: Pubkey,
+    pub provider: Pubkey,
+    pub payment_mint: Pubkey,
+    pub kind: AuctionKind,
+    pub quantity: u64,
+    pub start_price: u64,
+    pub reserve_price: u64,
+    pub min_increment: u64,
+    pub start_time: i64,
+    pub end_time: i64,
+    // Bids placed within this many seconds of the end push the end back
+    pub extension_window: i64,
+    pub highest_bid: u64,
+    pub highest_bidder: Option<Pubkey>,
+    pub bump: u8,
+}
+
+impl Auction {
+    pub const LEN: usize = 8 + 32 + 32 + 32 + 1 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 33 + 1;
+
+    pub fn is_live(&self, now: i64) -> bool {
+        now >= self.start_time && now < self.end_time
+    }
+
+    // Smallest bid an English auction currently accepts
+    pub fn min_next_bid(&self) -> Result<u64> {
+        match self.highest_bidder {
+            Some(_) => self.highest_bid.checked_add(self.min_increment)
+                .ok_or(error!(crate::ResourceError::PriceOverflow)),
+            None => Ok(self.reserve_price),
+        }
+    }
+
+    // Dutch auction price at `now`, rounded up so it never undercuts the
+    // linear schedule
+    pub fn current_price(&self, now: i64) -> u64 {
+        if now <= self.start_time {
+            return self.start_price;
+        }
+        if now >= self.end_time {
+            return self.reserve_price;
+        }
+        let drop = (self.start_price - self.reserve_price) as u128;
+        let elapsed = (now - self.start_time) as u128;
+        let duration = (self.end_time - self.start_time) as u128;
+        let discount = drop * elapsed / duration;
+        self.start_price - discount as u64
+    }
+}
+
+#[event]
+pub struct AuctionCreated {
+    pub auction: Pubkey,
+    pub resource: Pubkey,
+    pub kind: AuctionKind,
+    pub reserve_price: u64,
+    pub start_time: i64,
+    pub end_time: i64,
+}
+
+#[event]
+pub struct BidPlaced {
+    pub auction: Pubkey,
+    pub bidder: Pubkey,
+    pub amount: u64,
+    pub end_time: i64,
+}
+
+#[event]
+pub struct AuctionSettled {
+    pub auction: Pubkey,
+    pub resource: Pubkey,
+    pub winner: Option<Pubkey>,
+    pub amount: u64,
+}
+
+// Time the other party has to answer a dispute
+pub const DISPUTE_RESPONSE_PERIOD: i64 = 3 * 24 * 60 * 60;
+// Time arbiters have to rule once a dispute is answered
+pub const DISPUTE_RULING_PERIOD: i64 = 7 * 24 * 60 * 60;
+pub const MAX_EVIDENCE_URI_LEN: usize = 200;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq)]
+pub enum EscrowStatus {
+    Funded,
+    Disputed,
+    Released,
+    Refunded,
+    Split,
+}
+
+// Purchase whose payment is held until the buyer confirms delivery or the
+// inspection period passes. PDA at ["escrow", resource, buyer, purchase index];
+// funds sit in ["escrow_vault", escrow].
+#[account]
+pub struct Escrow {
+    pub resource: Pubkey,
+    pub buyer: Pubkey,
+    pub provider: Pubkey,
+    pub payment_mint: Pubkey,
+    pub index: u64,
+    pub quantity: u64,
+    pub amount: u64,
+    pub created_at: i64,
+    // Provider may claim the funds after this time if no dispute is open
+    pub release_after: i64,
+    pub status: EscrowStatus,
+    pub bump: u8,
+}
+
+impl Escrow {
+    pub const LEN: usize = 8 + 32 + 32 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 1 + 1;
+}
+
+// Outcome of a dispute
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq)]
+pub enum Ruling {
+    // Pay the provider in full
+    Release,
+    // Return everything to the buyer
+    Refund,
+    // Give the buyer `buyer_bps` of the escrow and the provider the rest
+    Split { buyer_bps: u16 },
+}
+
+impl Ruling {
+    // (buyer amount, provider amount). Splits round the buyer's share down.
+    pub fn apportion(&self, amount: u64) -> Result<(u64, u64)> {
+        let buyer_amount = match self {
+            Ruling::Release => 0,
+            Ruling::Refund => amount,
+            Ruling::Split { buyer_bps } => {
+                if *buyer_bps as u64 > BPS_DENOMINATOR {
+                    return Err(error!(ExchangeError::InvalidShare));
+                }
+                (amount as u128 * *buyer_bps as u128 / BPS_DENOMINATOR as u128) as u64
+            }
+        };
+        Ok((buyer_amount, amount - buyer_amount))
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq)]
+pub enum DisputeStatus {
+    Open,
+    Responded,
+    Resolved,
+}
+
+// Evidence submitted by one party
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct Evidence {
+    pub uri: String,
+    pub hash: [u8; 32],
+}
+
+impl Evidence {
+    pub const LEN: usize = (4 + MAX_EVIDENCE_URI_LEN) + 32;
+}
+
+// Dispute over an escrow, PDA at ["dispute", escrow]
+#[account]
+pub struct Dispute {
+    pub escrow: Pubkey,
+    pub opened_by: Pubkey,
+    pub claimant_evidence: Evidence,
+    pub respondent_evidence: Option<Evidence>,
+    pub response_deadline: i64,
+    pub ruling_deadline: i64,
+    pub status: DisputeStatus,
+    pub ruling: Option<Ruling>,
+    pub resolved_by: Option<Pubkey>,
+    pub bump: u8,
+}
+
+impl Dispute {
+    pub const LEN: usize = 8 + 32 + 32 + Evidence::LEN + (1 + Evidence::LEN) + 8 + 8 + 1
+        + (1 + 1 + 2) + 33 + 1;
+
+    // Ruling applied when a deadline passes without action: an unanswered
+    // claim goes to the claimant, an unruled dispute is split evenly.
+    pub fn default_ruling(&self, escrow: &Escrow, now: i64) -> Option<Ruling> {
+        match self.status {
+            DisputeStatus::Open if now >= self.response_deadline => {
+                if self.opened_by == escrow.buyer {
+                    Some(Ruling::Refund)
+                } else {
+                    Some(Ruling::Release)
+                }
+            }
+            DisputeStatus::Responded if now >= self.ruling_deadline => {
+                Some(Ruling::Split { buyer_bps: (BPS_DENOMINATOR / 2) as u16 })
+            }
+            _ => None,
+        }
+    }
+}
+
+#[event]
+pub struct EscrowFunded {
+    pub escrow: Pubkey,
+    pub resource: Pubkey,
+    pub buyer: Pubkey,
+    pub amount: u64,
+    pub release_after: i64,
+}
+
+#[event]
+pub struct EscrowReleased {
+    pub escrow: Pubkey,
+    pub released_by: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct DisputeOpened {
+    pub dispute: Pubkey,
+    pub escrow: Pubkey,
+    pub opened_by: Pubkey,
+    pub evidence_hash: [u8; 32],
+    pub response_deadline: i64,
+}
+
+#[event]
+pub struct DisputeResponded {
+    pub dispute: Pubkey,
+    pub respondent: Pubkey,
+    pub evidence_hash: [u8; 32],
+    pub ruling_deadline: i64,
+}
+
+#[event]
+pub struct DisputeResolved {
+    pub dispute: Pubkey,
+    pub escrow: Pubkey,
+    pub ruling: Ruling,
+    pub buyer_amount: u64,
+    pub provider_amount: u64,
+    // None when the outcome was applied by default after a deadline
+    pub resolved_by: Option<Pubkey>,
+}
+
+// Exchange errors
+#[error_code]
+pub enum ExchangeError {
+    #[msg("Resource does not support this exchange type")]
+    WrongExchangeType,
+    #[msg("Offer is not in the required status")]
+    InvalidOfferStatus,
+    #[msg("Signer is not a party to this exchange")]
+    UnauthorizedParty,
+    #[msg("Collaboration has not been activated")]
+    CollaborationNotActive,
+    #[msg("Invalid revenue share or cap")]
+    InvalidShare,
+    #[msg("Total revenue shares would exceed 100%")]
+    ShareCapExceeded,
+    #[msg("Expiry must be in the future")]
+    InvalidExpiry,
+    #[msg("Every outstanding agreement must be included exactly once, in key order")]
+    AgreementSetMismatch,
+    #[msg("Agreement does not belong to this revenue ledger")]
+    InvalidAgreement,
+    #[msg("Offer has expired")]
+    OfferExpired,
+    #[msg("Only material and funding resources can be auctioned")]
+    NotAuctionable,
+    #[msg("Invalid auction parameters")]
+    InvalidAuctionParams,
+    #[msg("Auction is not accepting bids")]
+    AuctionNotLive,
+    #[msg("Auction has not ended")]
+    AuctionNotEnded,
+    #[msg("Bid is below the minimum accepted amount")]
+    BidTooLow,
+    #[msg("Outbid bidder's token account is required for the refund")]
+    MissingRefundAccount,
+    #[msg("Escrow is not in the required status")]
+    InvalidEscrowStatus,
+    #[msg("Escrow cannot be released yet")]
+    EscrowLocked,
+    #[msg("Dispute is not in the required status")]
+    InvalidDisputeStatus,
+    #[msg("Dispute deadline has passed")]
+    DisputeDeadlinePassed,
+    #[msg("No default ruling applies yet")]
+    DisputeDeadlineNotReached,
+    #[msg("Evidence URI too long")]
+    EvidenceTooLong,
+    #[msg("Purchase receipt account is required")]
+    MissingPurchaseReceipt,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn agreement(share_bps: u16, cap: u64, claimed: u64) -> FutureBenefitAgreement {
+        FutureBenefitAgreement {
+            resource: Pubkey::default(),
+            project: Pubkey::default(),
+            provider: Pubkey::default(),
+            mint: Pubkey::default(),
+            share_bps,
+            cap,
+            claimed,
+            expires_at: 0,
+            status: AgreementStatus::Active,
+            created_at: 0,
+            bump: 0,
+        }
+    }
+
+    fn auction(kind: AuctionKind, start_price: u64, reserve_price: u64) -> Auction {
+        Auction {
+            resource: Pubkey::default(),
+            provider: Pubkey::default(),
+            payment_mint: Pubkey::default(),
+            kind,
+            quantity: 1,
+            start_price,
+            reserve_price,
+            min_increment: 10,
+            start_time: 100,
+            end_time: 200,
+            extension_window: 0,
+            highest_bid: 0,
+            highest_bidder: None,
+            bump: 0,
+        }
+    }
+
+    #[test]
+    fn auction_is_live_between_start_and_end() {
+        let auction = auction(AuctionKind::English, 0, 50);
+        assert!(!auction.is_live(99));
+        assert!(auction.is_live(100));
+        assert!(auction.is_live(199));
+        assert!(!auction.is_live(200));
+    }
+
+    #[test]
+    fn english_auction_next_bid() {
+        let mut auction = auction(AuctionKind::English, 0, 50);
+        assert_eq!(auction.min_next_bid().unwrap(), 50);
+
+        auction.highest_bid = 70;
+        auction.highest_bidder = Some(Pubkey::new_unique());
+        assert_eq!(auction.min_next_bid().unwrap(), 80);
+
+        auction.highest_bid = u64::MAX;
+        assert!(auction.min_next_bid().is_err());
+    }
+
+    #[test]
+    fn dutch_price_falls_linearly_and_rounds_up() {
+        let auction = auction(AuctionKind::Dutch, 1_000, 100);
+        assert_eq!(auction.current_price(50), 1_000);
+        assert_eq!(auction.current_price(100), 1_000);
+        assert_eq!(auction.current_price(150), 550);
+        assert_eq!(auction.current_price(200), 100);
+        assert_eq!(auction.current_price(300), 100);
+
+        // A discount of 2.31 is truncated so the price stays on or above the line
+        let uneven = Auction { start_price: 1_000, reserve_price: 993, ..auction };
+        assert_eq!(uneven.current_price(133), 998);
+    }
+
+    #[test]
+    fn rulings_apportion_the_escrow() {
+        assert_eq!(Ruling::Release.apportion(1_000).unwrap(), (0, 1_000));
+        assert_eq!(Ruling::Refund.apportion(1_000).unwrap(), (1_000, 0));
+        assert_eq!(Ruling::Split { buyer_bps: 5_000 }.apportion(1_001).unwrap(), (500, 501));
+        assert_eq!(Ruling::Split { buyer_bps: 10_000 }.apportion(7).unwrap(), (7, 0));
+        assert_eq!(Ruling::Split { buyer_bps: 0 }.apportion(7).unwrap(), (0, 7));
+        assert!(Ruling::Split { buyer_bps: 10_001 }.apportion(7).is_err());
+    }
+
+    fn escrow(buyer: Pubkey, provider: Pubkey) -> Escrow {
+        Escrow {
+            resource: Pubkey::default(),
+            buyer,
+            provider,
+            payment_mint: Pubkey::default(),
+            index: 0,
+            quantity: 1,
+            amount: 100,
+            created_at: 0,
+            release_after: 0,
+            status: EscrowStatus::Disputed,
+            bump: 0,
+        }
+    }
+
+    fn dispute(opened_by: Pubkey, status: DisputeStatus) -> Dispute {
+        let evidence = Evidence { uri: String::new(), hash: [0; 32] };
+        Dispute {
+            escrow: Pubkey::default(),
+            opened_by,
+            claimant_evidence: evidence,
+            respondent_evidence: None,
+            response_deadline: 100,
+            ruling_deadline: 200,
+            status,
+            ruling: None,
+            resolved_by: None,
+            bump: 0,
+        }
+    }
+
+    #[test]
+    fn unanswered_dispute_defaults_to_the_claimant() {
+        let (buyer, provider) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let escrow = escrow(buyer, provider);
+
+        let by_buyer = dispute(buyer, DisputeStatus::Open);
+        assert!(by_buyer.default_ruling(&escrow, 99).is_none());
+        assert!(by_buyer.default_ruling(&escrow, 100) == Some(Ruling::Refund));
+
+        let by_provider = dispute(provider, DisputeStatus::Open);
+        assert!(by_provider.default_ruling(&escrow, 100) == Some(Ruling::Release));
+    }
+
+    #[test]
+    fn unruled_dispute_defaults_to_an_even_split() {
+        let (buyer, provider) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let escrow = escrow(buyer, provider);
+
+        let responded = dispute(buyer, DisputeStatus::Responded);
+        assert!(responded.default_ruling(&escrow, 199).is_none());
+        assert!(responded.default_ruling(&escrow, 200) == Some(Ruling::Split { buyer_bps: 5_000 }));
+        assert!(dispute(buyer, DisputeStatus::Resolved).default_ruling(&escrow, 1_000).is_none());
+    }
+
+    #[test]
+    fn payout_rounds_down_and_respects_cap() {
+        assert_eq!(agreement(2_500, 1_000, 0).payout_for(1_000), 250);
+        assert_eq!(agreement(3_333, 1_000, 0).payout_for(10), 3);
+        assert_eq!(agreement(5_000, 1_000, 900).payout_for(1_000), 100);
+        assert_eq!(agreement(5_000, 1_000, 1_000).payout_for(1_000), 0);
+        assert_eq!(agreement(10_000, u64::MAX, 0).payout_for(u64::MAX), u64::MAX);
+    }
+}
@@ -0,0 +1,957 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Mint, Token, TokenAccount};
+use crate::access_control::{require_investor, AccessControl, ProjectInvestor};
+use crate::funding_standards::*;
+use crate::platform_instructions::*;
+use crate::token_instructions::{freeze_holder_account, thaw_holder_account};
+use crate::token_standards::{TokenHolder, TokenInfo};
+use crate::*;
+
+#[derive(Accounts)]
+pub struct CreateFunding<'info> {
+    #[account(has_one = creator)]
+    pub project: Account<'info, Project>,
+    #[account(
+        init,
+        payer = creator,
+        space = Funding::LEN,
+        seeds = [b"funding", project.key().as_ref()],
+        bump
+    )]
+    pub funding: Account<'info, Funding>,
+    pub mint: Account<'info, Mint>,
+    #[account(
+        init,
+        payer = creator,
+        seeds = [b"funding_vault", funding.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = funding
+    )]
+    pub vault: Account<'info, TokenAccount>,
+    pub platform: ProjectsLive<'info>,
+    #[account(mut)]
+    pub creator: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct AddMilestone<'info> {
+    #[account(
+        mut,
+        seeds = [b"funding", funding.project.as_ref()],
+        bump = funding.bump
+    )]
+    pub funding: Account<'info, Funding>,
+    #[account(address = funding.project, has_one = creator)]
+    pub project: Account<'info, Project>,
+    #[account(
+        init,
+        payer = creator,
+        space = Milestone::LEN,
+        seeds = [
+            b"milestone",
+            funding.key().as_ref(),
+            &funding.milestone_count.to_le_bytes(),
+        ],
+        bump
+    )]
+    pub milestone: Account<'info, Milestone>,
+    pub platform: ProjectsLive<'info>,
+    #[account(mut)]
+    pub creator: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DepositFunds<'info> {
+    #[account(
+        mut,
+        seeds = [b"funding", funding.project.as_ref()],
+        bump = funding.bump
+    )]
+    pub funding: Account<'info, Funding>,
+    #[account(address = funding.project)]
+    pub project: Account<'info, Project>,
+    #[account(
+        init_if_needed,
+        payer = investor,
+        space = InvestorPosition::LEN,
+        seeds = [b"investor", funding.key().as_ref(), investor.key().as_ref()],
+        bump
+    )]
+    pub position: Account<'info, InvestorPosition>,
+    #[account(mut, seeds = [b"funding_vault", funding.key().as_ref()], bump)]
+    pub vault: Account<'info, TokenAccount>,
+    #[account(mut, token::mint = funding.mint, token::authority = investor)]
+    pub investor_token_account: Account<'info, TokenAccount>,
+    pub platform: ProjectsLive<'info>,
+    #[account(mut)]
+    pub investor: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveMilestone<'info> {
+    #[account(
+        mut,
+        seeds = [b"funding", funding.project.as_ref()],
+        bump = funding.bump
+    )]
+    pub funding: Account<'info, Funding>,
+    #[account(
+        mut,
+        has_one = funding,
+        seeds = [b"milestone", funding.key().as_ref(), &milestone.index.to_le_bytes()],
+        bump = milestone.bump
+    )]
+    pub milestone: Account<'info, Milestone>,
+    pub platform: ProjectsLive<'info>,
+    pub reviewer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct VoteMilestone<'info> {
+    #[account(
+        mut,
+        seeds = [b"funding", funding.project.as_ref()],
+        bump = funding.bump
+    )]
+    pub funding: Account<'info, Funding>,
+    #[account(address = funding.project)]
+    pub project: Account<'info, Project>,
+    #[account(
+        mut,
+        has_one = funding,
+        seeds = [b"milestone", funding.key().as_ref(), &milestone.index.to_le_bytes()],
+        bump = milestone.bump
+    )]
+    pub milestone: Account<'info, Milestone>,
+    #[account(
+        has_one = funding,
+        has_one = investor,
+        seeds = [b"investor", funding.key().as_ref(), investor.key().as_ref()],
+        bump = position.bump
+    )]
+    pub position: Account<'info, InvestorPosition>,
+    #[account(
+        init,
+        payer = investor,
+        space = MilestoneVote::LEN,
+        seeds = [b"milestone_vote", milestone.key().as_ref(), investor.key().as_ref()],
+        bump
+    )]
+    pub vote: Account<'info, MilestoneVote>,
+    pub platform: ProjectsLive<'info>,
+    #[account(mut)]
+    pub investor: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseMilestone<'info> {
+    #[account(
+        mut,
+        seeds = [b"funding", funding.project.as_ref()],
+        bump = funding.bump
+    )]
+    pub funding: Account<'info, Funding>,
+    #[account(
+        mut,
+        has_one = funding,
+        seeds = [b"milestone", funding.key().as_ref(), &milestone.index.to_le_bytes()],
+        bump = milestone.bump
+    )]
+    pub milestone: Account<'info, Milestone>,
+    #[account(mut, seeds = [b"funding_vault", funding.key().as_ref()], bump)]
+    pub vault: Account<'info, TokenAccount>,
+    #[account(address = funding.project)]
+    pub project: Account<'info, Project>,
+    #[account(mut, token::mint = funding.mint, token::authority = project.creator)]
+    pub creator_token_account: Account<'info, TokenAccount>,
+    pub platform: ProjectsLive<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct MarkMilestoneMissed<'info> {
+    #[account(
+        mut,
+        seeds = [b"funding", funding.project.as_ref()],
+        bump = funding.bump
+    )]
+    pub funding: Account<'info, Funding>,
+    #[account(
+        mut,
+        has_one = funding,
+        seeds = [b"milestone", funding.key().as_ref(), &milestone.index.to_le_bytes()],
+        bump = milestone.bump
+    )]
+    pub milestone: Account<'info, Milestone>,
+    #[account(seeds = [b"funding_vault", funding.key().as_ref()], bump)]
+    pub vault: Account<'info, TokenAccount>,
+    pub platform: ProjectsLive<'info>,
+}
+
+// Open while projects are paused, so investors can always leave a failed funding
+#[derive(Accounts)]
+pub struct ReclaimFunds<'info> {
+    #[account(seeds = [b"funding", funding.project.as_ref()], bump = funding.bump)]
+    pub funding: Account<'info, Funding>,
+    #[account(
+        mut,
+        has_one = funding,
+        has_one = investor,
+        seeds = [b"investor", funding.key().as_ref(), investor.key().as_ref()],
+        bump = position.bump
+    )]
+    pub position: Account<'info, InvestorPosition>,
+    #[account(mut, seeds = [b"funding_vault", funding.key().as_ref()], bump)]
+    pub vault: Account<'info, TokenAccount>,
+    #[account(mut, token::mint = funding.mint, token::authority = investor)]
+    pub investor_token_account: Account<'info, TokenAccount>,
+    pub investor: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct CreateCampaign<'info> {
+    #[account(has_one = creator)]
+    pub project: Account<'info, Project>,
+    #[account(
+        init,
+        payer = creator,
+        space = Campaign::LEN,
+        seeds = [b"campaign", project.key().as_ref()],
+        bump
+    )]
+    pub campaign: Account<'info, Campaign>,
+    #[account(
+        mut,
+        seeds = [b"token_info", project_mint.key().as_ref()],
+        bump = token_info.bump,
+        constraint = token_info.authority == creator.key() @ TokenError::UnauthorizedOperation
+    )]
+    pub token_info: Account<'info, TokenInfo>,
+    pub accepted_mint: Account<'info, Mint>,
+    #[account(
+        init,
+        payer = creator,
+        seeds = [b"campaign_vault", campaign.key().as_ref()],
+        bump,
+        token::mint = accepted_mint,
+        token::authority = campaign
+    )]
+    pub vault: Account<'info, TokenAccount>,
+    #[account(mut, address = token_info.mint)]
+    pub project_mint: Account<'info, Mint>,
+    #[account(
+        init,
+        payer = creator,
+        seeds = [b"campaign_tokens", campaign.key().as_ref()],
+        bump,
+        token::mint = project_mint,
+        token::authority = campaign
+    )]
+    pub token_vault: Account<'info, TokenAccount>,
+    pub platform: ProjectsLive<'info>,
+    #[account(mut)]
+    pub creator: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct Contribute<'info> {
+    #[account(
+        mut,
+        seeds = [b"campaign", campaign.project.as_ref()],
+        bump = campaign.bump
+    )]
+    pub campaign: Account<'info, Campaign>,
+    #[account(
+        init_if_needed,
+        payer = contributor,
+        space = Contribution::LEN,
+        seeds = [b"contribution", campaign.key().as_ref(), contributor.key().as_ref()],
+        bump
+    )]
+    pub contribution: Account<'info, Contribution>,
+    #[account(mut, seeds = [b"campaign_vault", campaign.key().as_ref()], bump)]
+    pub vault: Account<'info, TokenAccount>,
+    #[account(mut, token::mint = campaign.accepted_mint, token::authority = contributor)]
+    pub contributor_token_account: Account<'info, TokenAccount>,
+    // Investor credentials, required when the campaign is investors-only
+    #[account(seeds = [b"access_control"], bump)]
+    pub access_control: Option<Account<'info, AccessControl>>,
+    #[account(
+        seeds = [b"project_investor", campaign.project.as_ref(), contributor.key().as_ref()],
+        bump = investor_approval.bump
+    )]
+    pub investor_approval: Option<Account<'info, ProjectInvestor>>,
+    pub platform: ProjectsLive<'info>,
+    #[account(mut)]
+    pub contributor: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizeCampaign<'info> {
+    #[account(
+        mut,
+        has_one = token_info,
+        seeds = [b"campaign", campaign.project.as_ref()],
+        bump = campaign.bump
+    )]
+    pub campaign: Account<'info, Campaign>,
+    #[account(mut, has_one = mint)]
+    pub token_info: Account<'info, TokenInfo>,
+    #[account(mut)]
+    pub mint: Account<'info, Mint>,
+    #[account(mut, seeds = [b"campaign_vault", campaign.key().as_ref()], bump)]
+    pub vault: Account<'info, TokenAccount>,
+    #[account(mut, seeds = [b"campaign_tokens", campaign.key().as_ref()], bump)]
+    pub token_vault: Account<'info, TokenAccount>,
+    #[account(address = campaign.project)]
+    pub project: Account<'info, Project>,
+    #[account(mut, token::mint = campaign.accepted_mint, token::authority = project.creator)]
+    pub creator_token_account: Account<'info, TokenAccount>,
+    pub platform: ProjectsLive<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimCampaignTokens<'info> {
+    #[account(
+        has_one = token_info,
+        seeds = [b"campaign", campaign.project.as_ref()],
+        bump = campaign.bump
+    )]
+    pub campaign: Account<'info, Campaign>,
+    #[account(
+        mut,
+        close = contributor,
+        has_one = campaign,
+        has_one = contributor,
+        seeds = [b"contribution", campaign.key().as_ref(), contributor.key().as_ref()],
+        bump = contribution.bump
+    )]
+    pub contribution: Account<'info, Contribution>,
+    #[account(mut)]
+    pub token_info: Account<'info, TokenInfo>,
+    #[account(address = token_info.mint)]
+    pub mint: Account<'info, Mint>,
+    #[account(mut, seeds = [b"campaign_tokens", campaign.key().as_ref()], bump)]
+    pub token_vault: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        has_one = token_info,
+        constraint = holder.owner == contributor.key() @ TokenError::UnauthorizedOperation,
+        seeds = [b"holder", token_info.key().as_ref(), holder.owner.as_ref()],
+        bump = holder.bump
+    )]
+    pub holder: Account<'info, TokenHolder>,
+    #[account(mut, address = holder.token_account)]
+    pub to: Account<'info, TokenAccount>,
+    pub platform: ProjectsLive<'info>,
+    #[account(mut)]
+    pub contributor: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+// Open while projects are paused, so contributors can always take back a failed raise
+#[derive(Accounts)]
+pub struct RefundContribution<'info> {
+    #[account(seeds = [b"campaign", campaign.project.as_ref()], bump = campaign.bump)]
+    pub campaign: Account<'info, Campaign>,
+    #[account(
+        mut,
+        close = contributor,
+        has_one = campaign,
+        has_one = contributor,
+        seeds = [b"contribution", campaign.key().as_ref(), contributor.key().as_ref()],
+        bump = contribution.bump
+    )]
+    pub contribution: Account<'info, Contribution>,
+    #[account(mut, seeds = [b"campaign_vault", campaign.key().as_ref()], bump)]
+    pub vault: Account<'info, TokenAccount>,
+    #[account(mut, token::mint = campaign.accepted_mint, token::authority = contributor)]
+    pub contributor_token_account: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub contributor: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+// Funding instruction handlers
+pub fn handle_create_funding(
+    ctx: Context<CreateFunding>,
+    reviewer: Option<Pubkey>,
+    approval_threshold_bps: u16,
+) -> Result<()> {
+    if approval_threshold_bps == 0
+        || approval_threshold_bps as u64 > crate::exchange_standards::BPS_DENOMINATOR
+    {
+        return Err(error!(FundingError::InvalidFundingParams));
+    }
+    // The creator cannot approve their own milestones
+    if reviewer == Some(ctx.accounts.creator.key()) {
+        return Err(error!(FundingError::UnauthorizedReviewer));
+    }
+
+    let funding = &mut ctx.accounts.funding;
+    funding.project = ctx.accounts.project.key();
+    funding.mint = ctx.accounts.mint.key();
+    funding.reviewer = reviewer;
+    funding.approval_threshold_bps = approval_threshold_bps;
+    funding.milestone_count = 0;
+    funding.pending_milestones = 0;
+    funding.unreleased_amount = 0;
+    funding.total_deposited = 0;
+    funding.total_released = 0;
+    funding.failed = false;
+    funding.refund_pool = 0;
+    funding.bump = *ctx.bumps.get("funding").unwrap();
+
+    Ok(())
+}
+
+// Milestones are fixed before the first deposit so investors fund known terms
+pub fn handle_add_milestone(
+    ctx: Context<AddMilestone>,
+    amount: u64,
+    deadline: i64,
+    uri: String,
+) -> Result<()> {
+    let funding = &mut ctx.accounts.funding;
+    let milestone = &mut ctx.accounts.milestone;
+
+    if funding.total_deposited > 0 {
+        return Err(error!(FundingError::FundingLocked));
+    }
+    if amount == 0 || deadline <= Clock::get()?.unix_timestamp {
+        return Err(error!(FundingError::InvalidFundingParams));
+    }
+    if uri.len() > MAX_MILESTONE_URI_LEN {
+        return Err(error!(ResourceError::FieldTooLong));
+    }
+
+    milestone.funding = funding.key();
+    milestone.index = funding.milestone_count;
+    milestone.amount = amount;
+    milestone.deadline = deadline;
+    milestone.uri = uri;
+    milestone.votes_for = 0;
+    milestone.status = MilestoneStatus::Pending;
+    milestone.bump = *ctx.bumps.get("milestone").unwrap();
+
+    funding.add_tranche(amount)?;
+
+    Ok(())
+}
+
+pub fn handle_deposit_funds(ctx: Context<DepositFunds>, amount: u64) -> Result<()> {
+    let funding = &mut ctx.accounts.funding;
+    let position = &mut ctx.accounts.position;
+
+    require_independent_investor(&ctx.accounts.project.creator, &ctx.accounts.investor.key())?;
+    funding.require_accepting_deposits()?;
+    if amount == 0 {
+        return Err(error!(FundingError::InvalidFundingParams));
+    }
+
+    token::transfer(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            token::Transfer {
+                from: ctx.accounts.investor_token_account.to_account_info(),
+                to: ctx.accounts.vault.to_account_info(),
+                authority: ctx.accounts.investor.to_account_info(),
+            },
+        ),
+        amount,
+    )?;
+
+    if position.deposited == 0 {
+        position.funding = funding.key();
+        position.investor = ctx.accounts.investor.key();
+        position.refunded = false;
+        position.bump = *ctx.bumps.get("position").unwrap();
+    }
+    position.deposited = position.deposited.checked_add(amount)
+        .ok_or(FundingError::AmountOverflow)?;
+    funding.total_deposited = funding.total_deposited.checked_add(amount)
+        .ok_or(FundingError::AmountOverflow)?;
+
+    emit!(FundsDeposited {
+        funding: funding.key(),
+        investor: position.investor,
+        amount,
+    });
+
+    Ok(())
+}
+
+// The designated reviewer approves a milestone outright
+pub fn handle_approve_milestone(ctx: Context<ApproveMilestone>) -> Result<()> {
+    let funding = &mut ctx.accounts.funding;
+    let milestone = &mut ctx.accounts.milestone;
+    let reviewer = ctx.accounts.reviewer.key();
+
+    if funding.reviewer != Some(reviewer) {
+        return Err(error!(FundingError::UnauthorizedReviewer));
+    }
+    ensure_open(funding, milestone)?;
+
+    milestone.status = MilestoneStatus::Approved;
+    funding.pending_milestones = funding.pending_milestones.saturating_sub(1);
+
+    emit!(MilestoneApproved {
+        milestone: milestone.key(),
+        votes_for: milestone.votes_for,
+        reviewer: Some(reviewer),
+    });
+
+    Ok(())
+}
+
+// Investors vote with their deposit; the milestone is approved once the
+// funding's threshold of total deposits is reached
+pub fn handle_vote_milestone(ctx: Context<VoteMilestone>) -> Result<()> {
+    let funding = &mut ctx.accounts.funding;
+    let milestone = &mut ctx.accounts.milestone;
+    let weight = ctx.accounts.position.deposited;
+
+    require_independent_investor(&ctx.accounts.project.creator, &ctx.accounts.investor.key())?;
+    ensure_open(funding, milestone)?;
+
+    let vote = &mut ctx.accounts.vote;
+    vote.milestone = milestone.key();
+    vote.investor = ctx.accounts.investor.key();
+    vote.weight = weight;
+
+    milestone.votes_for = milestone.votes_for.checked_add(weight)
+        .ok_or(FundingError::AmountOverflow)?;
+    if funding.vote_passes(milestone.votes_for) {
+        milestone.status = MilestoneStatus::Approved;
+        funding.pending_milestones = funding.pending_milestones.saturating_sub(1);
+
+        emit!(MilestoneApproved {
+            milestone: milestone.key(),
+            votes_for: milestone.votes_for,
+            reviewer: None,
+        });
+    }
+
+    Ok(())
+}
+
+// Anyone pays out an approved tranche to the project creator
+pub fn handle_release_milestone(ctx: Context<ReleaseMilestone>) -> Result<()> {
+    let funding = &ctx.accounts.funding;
+    let milestone = &ctx.accounts.milestone;
+
+    if funding.failed {
+        return Err(error!(FundingError::FundingFailed));
+    }
+    if milestone.status != MilestoneStatus::Approved {
+        return Err(error!(FundingError::InvalidMilestoneStatus));
+    }
+
+    // An under-subscribed funding never pays out what the other tranches are owed
+    let amount = funding.release_amount(milestone.amount);
+    transfer_from_vault(
+        funding,
+        &ctx.accounts.token_program,
+        &ctx.accounts.vault,
+        &ctx.accounts.creator_token_account,
+        amount,
+    )?;
+
+    let milestone = &mut ctx.accounts.milestone;
+    ctx.accounts.funding.record_release(milestone.amount, amount)?;
+    milestone.status = MilestoneStatus::Released;
+
+    emit!(MilestoneReleased {
+        milestone: milestone.key(),
+        amount,
+    });
+
+    Ok(())
+}
+
+// Anyone flags a milestone whose deadline passed without approval, failing
+// the funding and snapshotting the unreleased balance for refunds
+pub fn handle_mark_milestone_missed(ctx: Context<MarkMilestoneMissed>) -> Result<()> {
+    let funding = &mut ctx.accounts.funding;
+    let milestone = &mut ctx.accounts.milestone;
+
+    if funding.failed {
+        return Err(error!(FundingError::FundingFailed));
+    }
+    if milestone.status != MilestoneStatus::Pending {
+        return Err(error!(FundingError::InvalidMilestoneStatus));
+    }
+    if Clock::get()?.unix_timestamp <= milestone.deadline {
+        return Err(error!(FundingError::MilestoneDeadlineNotReached));
+    }
+
+    milestone.status = MilestoneStatus::Missed;
+    funding.failed = true;
+    funding.refund_pool = ctx.accounts.vault.amount;
+
+    emit!(FundingFailed {
+        funding: funding.key(),
+        milestone: milestone.key(),
+        refund_pool: funding.refund_pool,
+    });
+
+    Ok(())
+}
+
+// Investors of a failed funding reclaim their pro-rata share of the
+// unreleased balance
+pub fn handle_reclaim_funds(ctx: Context<ReclaimFunds>) -> Result<()> {
+    let funding = &ctx.accounts.funding;
+    let position = &ctx.accounts.position;
+
+    if !funding.failed {
+        return Err(error!(FundingError::FundingNotFailed));
+    }
+    let amount = funding.refund_share(position.deposited);
+    if position.refunded || amount == 0 {
+        return Err(error!(FundingError::NothingToRefund));
+    }
+
+    transfer_from_vault(
+        funding,
+        &ctx.accounts.token_program,
+        &ctx.accounts.vault,
+        &ctx.accounts.investor_token_account,
+        amount,
+    )?;
+
+    let position = &mut ctx.accounts.position;
+    position.refunded = true;
+
+    emit!(InvestorRefunded {
+        funding: funding.key(),
+        investor: position.investor,
+        amount,
+    });
+
+    Ok(())
+}
+
+fn ensure_open(funding: &Funding, milestone: &Milestone) -> Result<()> {
+    if funding.failed {
+        return Err(error!(FundingError::FundingFailed));
+    }
+    if milestone.status != MilestoneStatus::Pending {
+        return Err(error!(FundingError::InvalidMilestoneStatus));
+    }
+    if Clock::get()?.unix_timestamp > milestone.deadline {
+        return Err(error!(FundingError::MilestoneDeadlinePassed));
+    }
+    Ok(())
+}
+
+fn transfer_from_vault<'info>(
+    funding: &Account<'info, Funding>,
+    token_program: &Program<'info, Token>,
+    vault: &Account<'info, TokenAccount>,
+    to: &Account<'info, TokenAccount>,
+    amount: u64,
+) -> Result<()> {
+    let seeds = &[b"funding", funding.project.as_ref(), &[funding.bump]];
+    token::transfer(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            token::Transfer {
+                from: vault.to_account_info(),
+                to: to.to_account_info(),
+                authority: funding.to_account_info(),
+            },
+            &[&seeds[..]],
+        ),
+        amount,
+    )
+}
+
+// Campaign instruction handlers
+#[allow(clippy::too_many_arguments)]
+pub fn handle_create_campaign(
+    ctx: Context<CreateCampaign>,
+    soft_cap: u64,
+    hard_cap: u64,
+    start_time: i64,
+    end_time: i64,
+    min_contribution: u64,
+    max_contribution: u64,
+    token_rate: u64,
+    investors_only: bool,
+) -> Result<()> {
+    let campaign = &mut ctx.accounts.campaign;
+
+    campaign.project = ctx.accounts.project.key();
+    campaign.token_info = ctx.accounts.token_info.key();
+    campaign.accepted_mint = ctx.accounts.accepted_mint.key();
+    campaign.soft_cap = soft_cap;
+    campaign.hard_cap = hard_cap;
+    campaign.start_time = start_time;
+    campaign.end_time = end_time;
+    campaign.min_contribution = min_contribution;
+    campaign.max_contribution = max_contribution;
+    campaign.token_rate = token_rate;
+    campaign.investors_only = investors_only;
+    campaign.total_raised = 0;
+    campaign.contributor_count = 0;
+    campaign.status = CampaignStatus::Active;
+    campaign.finalized_at = 0;
+    campaign.bump = *ctx.bumps.get("campaign").unwrap();
+    campaign.validate()?;
+
+    // Mint the tokens for the whole hard cap up front, so settling the
+    // campaign never depends on the token authority
+    let tokens = campaign.tokens_for(hard_cap)?;
+    let token_info = &mut ctx.accounts.token_info;
+    token_info.reserve_supply(tokens)?;
+
+    let mint_key = ctx.accounts.project_mint.key();
+    let seeds = &[b"token_info", mint_key.as_ref(), &[token_info.bump]];
+    token::mint_to(
+        CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            token::MintTo {
+                mint: ctx.accounts.project_mint.to_account_info(),
+                to: ctx.accounts.token_vault.to_account_info(),
+                authority: token_info.to_account_info(),
+            },
+            &[&seeds[..]],
+        ),
+        tokens,
+    )?;
+
+    Ok(())
+}
+
+pub fn handle_contribute(ctx: Context<Contribute>, amount: u64) -> Result<()> {
+    let campaign = &mut ctx.accounts.campaign;
+    let contribution = &mut ctx.accounts.contribution;
+    let now = Clock::get()?.unix_timestamp;
+
+    if campaign.status != CampaignStatus::Active
+        || now < campaign.start_time
+        || now >= campaign.end_time
+    {
+        return Err(error!(FundingError::CampaignClosed));
+    }
+    if campaign.investors_only {
+        require_investor(
+            ctx.accounts.access_control.as_deref(),
+            ctx.accounts.investor_approval.as_deref(),
+            &campaign.project,
+            &ctx.accounts.contributor.key(),
+        )?;
+    }
+
+    let wallet_total = contribution.amount.checked_add(amount)
+        .ok_or(FundingError::AmountOverflow)?;
+    let total_raised = campaign.total_raised.checked_add(amount)
+        .ok_or(FundingError::AmountOverflow)?;
+    if amount == 0
+        || wallet_total < campaign.min_contribution
+        || wallet_total > campaign.max_contribution
+        || total_raised > campaign.hard_cap
+    {
+        return Err(error!(FundingError::InvalidContribution));
+    }
+
+    token::transfer(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            token::Transfer {
+                from: ctx.accounts.contributor_token_account.to_account_info(),
+                to: ctx.accounts.vault.to_account_info(),
+                authority: ctx.accounts.contributor.to_account_info(),
+            },
+        ),
+        amount,
+    )?;
+
+    if contribution.amount == 0 {
+        contribution.campaign = campaign.key();
+        contribution.contributor = ctx.accounts.contributor.key();
+        contribution.bump = *ctx.bumps.get("contribution").unwrap();
+        campaign.contributor_count = campaign.contributor_count.checked_add(1)
+            .ok_or(FundingError::AmountOverflow)?;
+    }
+    contribution.amount = wallet_total;
+    campaign.total_raised = total_raised;
+
+    emit!(CampaignContributed {
+        campaign: campaign.key(),
+        contributor: contribution.contributor,
+        amount,
+        total_raised,
+    });
+
+    Ok(())
+}
+
+// Settle a campaign once it has ended or filled; anyone may call this.
+// Reaching the soft cap releases the raise to the creator and keeps the
+// contributors' tokens in the campaign token vault; the tokens reserved for
+// the unsold part of the hard cap, or all of them on failure, are burned.
+pub fn handle_finalize_campaign(ctx: Context<FinalizeCampaign>) -> Result<()> {
+    let campaign = &ctx.accounts.campaign;
+    let now = Clock::get()?.unix_timestamp;
+
+    if campaign.status != CampaignStatus::Active {
+        return Err(error!(FundingError::InvalidCampaignStatus));
+    }
+    if !campaign.can_finalize(now) {
+        return Err(error!(FundingError::CampaignNotEnded));
+    }
+
+    let succeeded = campaign.total_raised >= campaign.soft_cap;
+    let tokens_sold = if succeeded {
+        campaign.tokens_for(campaign.total_raised)?
+    } else {
+        0
+    };
+    let unsold = campaign.tokens_for(campaign.hard_cap)? - tokens_sold;
+
+    if unsold > 0 {
+        let seeds = &[b"campaign", campaign.project.as_ref(), &[campaign.bump]];
+        token::burn(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::Burn {
+                    mint: ctx.accounts.mint.to_account_info(),
+                    from: ctx.accounts.token_vault.to_account_info(),
+                    authority: campaign.to_account_info(),
+                },
+                &[&seeds[..]],
+            ),
+            unsold,
+        )?;
+        ctx.accounts.token_info.release_reserved(unsold)?;
+    }
+
+    if succeeded {
+        transfer_from_campaign(
+            campaign,
+            &ctx.accounts.token_program,
+            &ctx.accounts.vault,
+            &ctx.accounts.creator_token_account,
+            campaign.total_raised,
+        )?;
+    }
+
+    let campaign = &mut ctx.accounts.campaign;
+    campaign.status = if succeeded {
+        CampaignStatus::Succeeded
+    } else {
+        CampaignStatus::Failed
+    };
+    campaign.finalized_at = now;
+
+    emit!(CampaignFinalized {
+        campaign: campaign.key(),
+        succeeded,
+        total_raised: campaign.total_raised,
+        tokens_sold,
+        tokens_burned: unsold,
+    });
+
+    Ok(())
+}
+
+// Contributors of a successful campaign claim their tokens, vesting from
+// the finalization time under the token's distribution rules
+pub fn handle_claim_campaign_tokens(ctx: Context<ClaimCampaignTokens>) -> Result<()> {
+    let campaign = &ctx.accounts.campaign;
+
+    if campaign.status != CampaignStatus::Succeeded {
+        return Err(error!(FundingError::InvalidCampaignStatus));
+    }
+
+    let tokens = campaign.tokens_for(ctx.accounts.contribution.amount)?;
+    ctx.accounts.token_info.distribute_reserved(
+        tokens,
+        &mut ctx.accounts.holder,
+        campaign.finalized_at,
+    )?;
+
+    let (token_info, mint, to) = (&ctx.accounts.token_info, &ctx.accounts.mint, &ctx.accounts.to);
+    thaw_holder_account(token_info, mint, to, &ctx.accounts.token_program)?;
+    transfer_from_campaign(
+        campaign,
+        &ctx.accounts.token_program,
+        &ctx.accounts.token_vault,
+        to,
+        tokens,
+    )?;
+    freeze_holder_account(token_info, mint, to, &ctx.accounts.token_program)?;
+
+    emit!(CampaignTokensClaimed {
+        campaign: campaign.key(),
+        contributor: ctx.accounts.contributor.key(),
+        tokens,
+    });
+
+    Ok(())
+}
+
+// Contributors of a failed campaign take their contribution back
+pub fn handle_refund_contribution(ctx: Context<RefundContribution>) -> Result<()> {
+    let campaign = &ctx.accounts.campaign;
+    let amount = ctx.accounts.contribution.amount;
+
+    if campaign.status != CampaignStatus::Failed {
+        return Err(error!(FundingError::InvalidCampaignStatus));
+    }
+
+    transfer_from_campaign(
+        campaign,
+        &ctx.accounts.token_program,
+        &ctx.accounts.vault,
+        &ctx.accounts.contributor_token_account,
+        amount,
+    )?;
+
+    emit!(CampaignRefunded {
+        campaign: campaign.key(),
+        contributor: ctx.accounts.contributor.key(),
+        amount,
+    });
+
+    Ok(())
+}
+
+fn transfer_from_campaign<'info>(
+    campaign: &Account<'info, Campaign>,
+    token_program: &Program<'info, Token>,
+    vault: &Account<'info, TokenAccount>,
+    to: &Account<'info, TokenAccount>,
+    amount: u64,
+) -> Result<()> {
+    let seeds = &[b"campaign", campaign.project.as_ref(), &[campaign.bump]];
+    token::transfer(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            token::Transfer {
+                from: vault.to_account_info(),
+                to: to.to_account_info(),
+                authority: campaign.to_account_info(),
+            },
+            &[&seeds[..]],
+        ),
+        amount,
+    )
+}
@@ -0,0 +1,480 @@
+use anchor_lang::prelude::*;
+use crate::exchange_standards::BPS_DENOMINATOR;
+
+pub const MAX_MILESTONE_URI_LEN: usize = 200;
+
+// Milestone-based funding pool of a project. PDA at ["funding", project];
+// deposits sit in the token account at ["funding_vault", funding].
+#[account]
+pub struct Funding {
+    // The project's current creator owns the funding
+    pub project: Pubkey,
+    pub mint: Pubkey,
+    // Optional designated reviewer who can approve milestones directly
+    pub reviewer: Option<Pubkey>,
+    // Share of deposits that must vote for a milestone to approve it
+    pub approval_threshold_bps: u16,
+    pub milestone_count: u32,
+    // Milestones still awaiting approval; deposits close once none remain
+    pub pending_milestones: u32,
+    // Sum of the tranches not yet released
+    pub unreleased_amount: u64,
+    pub total_deposited: u64,
+    pub total_released: u64,
+    // Set once a milestone deadline is missed; deposits and releases stop
+    pub failed: bool,
+    // Unreleased funds available for refunds when the project failed
+    pub refund_pool: u64,
+    pub bump: u8,
+}
+
+impl Funding {
+    pub const LEN: usize = 8 + 32 + 32 + 33 + 2 + 4 + 4 + 8 + 8 + 8 + 1 + 8 + 1;
+
+    // Record a new milestone's tranche
+    pub fn add_tranche(&mut self, amount: u64) -> Result<()> {
+        self.milestone_count = self.milestone_count.checked_add(1)
+            .ok_or(FundingError::AmountOverflow)?;
+        self.pending_milestones = self.pending_milestones.checked_add(1)
+            .ok_or(FundingError::AmountOverflow)?;
+        self.unreleased_amount = self.unreleased_amount.checked_add(amount)
+            .ok_or(FundingError::AmountOverflow)?;
+        Ok(())
+    }
+
+    // Deposits only fund milestones that can still be approved
+    pub fn require_accepting_deposits(&self) -> Result<()> {
+        if self.failed {
+            return Err(error!(FundingError::FundingFailed));
+        }
+        if self.pending_milestones == 0 {
+            return Err(error!(FundingError::NoPendingMilestone));
+        }
+        Ok(())
+    }
+
+    // Payout for a released tranche: its amount, capped by the deposits left
+    // after the other unreleased tranches are set aside
+    pub fn release_amount(&self, tranche: u64) -> u64 {
+        let available = self.total_deposited.saturating_sub(self.total_released);
+        let owed_elsewhere = self.unreleased_amount.saturating_sub(tranche);
+        tranche.min(available.saturating_sub(owed_elsewhere))
+    }
+
+    // Book a released tranche and the amount actually paid for it
+    pub fn record_release(&mut self, tranche: u64, amount: u64) -> Result<()> {
+        self.total_released = self.total_released.checked_add(amount)
+            .ok_or(FundingError::AmountOverflow)?;
+        self.unreleased_amount = self.unreleased_amount.saturating_sub(tranche);
+        Ok(())
+    }
+
+    // Whether `votes` (in deposited base units) meet the approval threshold
+    pub fn vote_passes(&self, votes: u64) -> bool {
+        votes as u128 * BPS_DENOMINATOR as u128
+            >= self.total_deposited as u128 * self.approval_threshold_bps as u128
+    }
+
+    // Investor's pro-rata share of the refund pool, rounded down
+    pub fn refund_share(&self, deposited: u64) -> u64 {
+        if self.total_deposited == 0 {
+            return 0;
+        }
+        (deposited as u128 * self.refund_pool as u128 / self.total_deposited as u128) as u64
+    }
+}
+
+// The project creator can neither fund nor vote on their own milestones
+pub fn require_independent_investor(creator: &Pubkey, investor: &Pubkey) -> Result<()> {
+    if creator == investor {
+        return Err(error!(FundingError::CreatorCannotInvest));
+    }
+    Ok(())
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq)]
+pub enum MilestoneStatus {
+    Pending,
+    Approved,
+    Released,
+    Missed,
+}
+
+// Tranche of a funding pool, PDA at ["milestone", funding, index]
+#[account]
+pub struct Milestone {
+    pub funding: Pubkey,
+    pub index: u32,
+    pub amount: u64,
+    pub deadline: i64,
+    pub uri: String,
+    pub votes_for: u64,
+    pub status: MilestoneStatus,
+    pub bump: u8,
+}
+
+impl Milestone {
+    pub const LEN: usize = 8 + 32 + 4 + 8 + 8 + (4 + MAX_MILESTONE_URI_LEN) + 8 + 1 + 1;
+}
+
+// Investor's stake in a funding pool, PDA at ["investor", funding, investor]
+#[account]
+pub struct InvestorPosition {
+    pub funding: Pubkey,
+    pub investor: Pubkey,
+    pub deposited: u64,
+    pub refunded: bool,
+    pub bump: u8,
+}
+
+impl InvestorPosition {
+    pub const LEN: usize = 8 + 32 + 32 + 8 + 1 + 1;
+}
+
+// Marks that an investor voted on a milestone, PDA at ["milestone_vote", milestone, investor]
+#[account]
+pub struct MilestoneVote {
+    pub milestone: Pubkey,
+    pub investor: Pubkey,
+    pub weight: u64,
+}
+
+impl MilestoneVote {
+    pub const LEN: usize = 8 + 32 + 32 + 8;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq)]
+pub enum CampaignStatus {
+    Active,
+    Succeeded,
+    Failed,
+}
+
+// Crowdfunding campaign of a project, PDA at ["campaign", project].
+// Contributions sit in ["campaign_vault", campaign]. Project tokens for the
+// whole hard cap are minted into ["campaign_tokens", campaign] at creation;
+// the unsold part is burned at finalization and the rest waits to be claimed.
+#[account]
+pub struct Campaign {
+    // The project's current creator receives the raise
+    pub project: Pubkey,
+    pub token_info: Pubkey,
+    pub accepted_mint: Pubkey,
+    pub soft_cap: u64,
+    pub hard_cap: u64,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub min_contribution: u64,
+    pub max_contribution: u64,
+    // Project tokens minted per base unit contributed
+    pub token_rate: u64,
+    // Restrict contributions to verified investors
+    pub investors_only: bool,
+    pub total_raised: u64,
+    pub contributor_count: u64,
+    pub status: CampaignStatus,
+    pub finalized_at: i64,
+    pub bump: u8,
+}
+
+impl Campaign {
+    pub const LEN: usize = 8 + 32 * 3 + 8 * 10 + 1 + 1 + 1;
+
+    // Campaign parameters must describe a window that can actually succeed
+    pub fn validate(&self) -> Result<()> {
+        if self.soft_cap == 0
+            || self.soft_cap > self.hard_cap
+            || self.start_time >= self.end_time
+            || self.min_contribution > self.max_contribution
+            || self.max_contribution == 0
+            || self.token_rate == 0
+        {
+            return Err(error!(FundingError::InvalidFundingParams));
+        }
+        Ok(())
+    }
+
+    // Project tokens owed for `amount` contributed
+    pub fn tokens_for(&self, amount: u64) -> Result<u64> {
+        amount.checked_mul(self.token_rate)
+            .ok_or(error!(FundingError::AmountOverflow))
+    }
+
+    // A campaign settles once it ends or fills its hard cap
+    pub fn can_finalize(&self, now: i64) -> bool {
+        now >= self.end_time || self.total_raised >= self.hard_cap
+    }
+}
+
+// A wallet's running contribution to a campaign,
+// PDA at ["contribution", campaign, contributor]
+#[account]
+pub struct Contribution {
+    pub campaign: Pubkey,
+    pub contributor: Pubkey,
+    pub amount: u64,
+    pub bump: u8,
+}
+
+impl Contribution {
+    pub const LEN: usize = 8 + 32 + 32 + 8 + 1;
+}
+
+#[event]
+pub struct CampaignContributed {
+    pub campaign: Pubkey,
+    pub contributor: Pubkey,
+    pub amount: u64,
+    pub total_raised: u64,
+}
+
+#[event]
+pub struct CampaignFinalized {
+    pub campaign: Pubkey,
+    pub succeeded: bool,
+    pub total_raised: u64,
+    pub tokens_sold: u64,
+    pub tokens_burned: u64,
+}
+
+#[event]
+pub struct CampaignTokensClaimed {
+    pub campaign: Pubkey,
+    pub contributor: Pubkey,
+    pub tokens: u64,
+}
+
+#[event]
+pub struct CampaignRefunded {
+    pub campaign: Pubkey,
+    pub contributor: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct FundsDeposited {
+    pub funding: Pubkey,
+    pub investor: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct MilestoneApproved {
+    pub milestone: Pubkey,
+    pub votes_for: u64,
+    // None when approved by investor vote
+    pub reviewer: Option<Pubkey>,
+}
+
+#[event]
+pub struct MilestoneReleased {
+    pub milestone: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct FundingFailed {
+    pub funding: Pubkey,
+    pub milestone: Pubkey,
+    pub refund_pool: u64,
+}
+
+#[event]
+pub struct InvestorRefunded {
+    pub funding: Pubkey,
+    pub investor: Pubkey,
+    pub amount: u64,
+}
+
+// Funding errors
+#[error_code]
+pub enum FundingError {
+    #[msg("Invalid funding parameters")]
+    InvalidFundingParams,
+    #[msg("Milestones cannot change once deposits have been made")]
+    FundingLocked,
+    #[msg("Funding has failed")]
+    FundingFailed,
+    #[msg("Funding has not failed")]
+    FundingNotFailed,
+    #[msg("Milestone is not in the required status")]
+    InvalidMilestoneStatus,
+    #[msg("Milestone deadline has passed")]
+    MilestoneDeadlinePassed,
+    #[msg("Milestone deadline has not passed")]
+    MilestoneDeadlineNotReached,
+    #[msg("Only the designated reviewer may do this")]
+    UnauthorizedReviewer,
+    #[msg("Nothing to refund")]
+    NothingToRefund,
+    #[msg("Campaign is not accepting contributions")]
+    CampaignClosed,
+    #[msg("Contribution outside the per-wallet limits or hard cap")]
+    InvalidContribution,
+    #[msg("Campaign cannot be finalized yet")]
+    CampaignNotEnded,
+    #[msg("Campaign is not in the required status")]
+    InvalidCampaignStatus,
+    #[msg("Funding amount overflow")]
+    AmountOverflow,
+    #[msg("The project creator cannot invest in or vote on their own funding")]
+    CreatorCannotInvest,
+    #[msg("No milestone is left to fund")]
+    NoPendingMilestone,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn funding(total_deposited: u64, refund_pool: u64, approval_threshold_bps: u16) -> Funding {
+        Funding {
+            project: Pubkey::default(),
+            mint: Pubkey::default(),
+            reviewer: None,
+            approval_threshold_bps,
+            milestone_count: 0,
+            pending_milestones: 0,
+            unreleased_amount: 0,
+            total_deposited,
+            total_released: 0,
+            failed: true,
+            refund_pool,
+            bump: 0,
+        }
+    }
+
+    fn campaign() -> Campaign {
+        Campaign {
+            project: Pubkey::default(),
+            token_info: Pubkey::default(),
+            accepted_mint: Pubkey::default(),
+            soft_cap: 500,
+            hard_cap: 1_000,
+            start_time: 100,
+            end_time: 200,
+            min_contribution: 10,
+            max_contribution: 100,
+            token_rate: 3,
+            investors_only: false,
+            total_raised: 0,
+            contributor_count: 0,
+            status: CampaignStatus::Active,
+            finalized_at: 0,
+            bump: 0,
+        }
+    }
+
+    #[test]
+    fn campaign_parameters_must_be_achievable() {
+        assert!(campaign().validate().is_ok());
+        assert!(Campaign { soft_cap: 1_000, ..campaign() }.validate().is_ok());
+
+        assert!(Campaign { soft_cap: 0, ..campaign() }.validate().is_err());
+        assert!(Campaign { soft_cap: 1_001, ..campaign() }.validate().is_err());
+        assert!(Campaign { end_time: 100, ..campaign() }.validate().is_err());
+        assert!(Campaign { min_contribution: 101, ..campaign() }.validate().is_err());
+        assert!(Campaign { min_contribution: 0, max_contribution: 0, ..campaign() }.validate().is_err());
+        assert!(Campaign { token_rate: 0, ..campaign() }.validate().is_err());
+    }
+
+    #[test]
+    fn campaign_tokens_follow_the_rate() {
+        let campaign = campaign();
+        assert_eq!(campaign.tokens_for(0).unwrap(), 0);
+        assert_eq!(campaign.tokens_for(1_000).unwrap(), 3_000);
+        assert!(campaign.tokens_for(u64::MAX).is_err());
+    }
+
+    #[test]
+    fn campaign_finalizes_when_ended_or_full() {
+        let campaign = campaign();
+        assert!(!campaign.can_finalize(199));
+        assert!(campaign.can_finalize(200));
+
+        let full = Campaign { total_raised: 1_000, ..campaign };
+        assert!(full.can_finalize(150));
+    }
+
+    #[test]
+    fn refund_share_is_pro_rata_and_rounds_down() {
+        let funding = funding(3_000, 1_000, 5_000);
+        assert_eq!(funding.refund_share(1_000), 333);
+        assert_eq!(funding.refund_share(2_000), 666);
+        assert_eq!(funding.refund_share(3_000), 1_000);
+        assert_eq!(funding.refund_share(0), 0);
+
+        // Large deposits do not overflow the intermediate product
+        let large = Funding { total_deposited: u64::MAX, refund_pool: u64::MAX, ..funding };
+        assert_eq!(large.refund_share(u64::MAX), u64::MAX);
+    }
+
+    #[test]
+    fn refund_share_with_no_deposits_is_zero() {
+        assert_eq!(funding(0, 1_000, 5_000).refund_share(1_000), 0);
+    }
+
+    #[test]
+    fn vote_passes_at_the_threshold() {
+        let funding = funding(1_000, 0, 6_000);
+        assert!(!funding.vote_passes(599));
+        assert!(funding.vote_passes(600));
+        assert!(funding.vote_passes(1_000));
+
+        let unanimous = Funding { approval_threshold_bps: 10_000, ..funding };
+        assert!(!unanimous.vote_passes(999));
+        assert!(unanimous.vote_passes(1_000));
+    }
+
+    #[test]
+    fn release_is_capped_by_what_other_tranches_are_owed() {
+        let mut funding = Funding { failed: false, ..funding(0, 0, 5_000) };
+        funding.add_tranche(600).unwrap();
+        funding.add_tranche(400).unwrap();
+        assert_eq!(funding.unreleased_amount, 1_000);
+
+        // Fully subscribed: each tranche gets its amount
+        let full = Funding { total_deposited: 1_000, ..funding };
+        assert_eq!(full.release_amount(600), 600);
+        assert_eq!(full.release_amount(400), 400);
+
+        // Under-subscribed: a release cannot eat into the other tranche's share
+        let short = Funding { total_deposited: 700, ..funding };
+        assert_eq!(short.release_amount(400), 100);
+        assert_eq!(short.release_amount(600), 300);
+
+        // Once a tranche is booked the remainder follows
+        let mut released = Funding { total_deposited: 1_000, ..funding };
+        let amount = released.release_amount(600);
+        released.record_release(600, amount).unwrap();
+        assert_eq!(released.total_released, 600);
+        assert_eq!(released.unreleased_amount, 400);
+        assert_eq!(released.release_amount(400), 400);
+    }
+
+    #[test]
+    fn deposits_need_a_pending_milestone() {
+        let mut funding = Funding { failed: false, ..funding(0, 0, 5_000) };
+        assert_eq!(funding.require_accepting_deposits(), Err(error!(FundingError::NoPendingMilestone)));
+
+        funding.add_tranche(100).unwrap();
+        assert!(funding.require_accepting_deposits().is_ok());
+
+        funding.pending_milestones = 0;
+        assert_eq!(funding.require_accepting_deposits(), Err(error!(FundingError::NoPendingMilestone)));
+
+        let failed = Funding { pending_milestones: 1, failed: true, ..funding };
+        assert_eq!(failed.require_accepting_deposits(), Err(error!(FundingError::FundingFailed)));
+    }
+
+    #[test]
+    fn creator_cannot_invest_or_vote() {
+        let creator = Pubkey::new_unique();
+        let investor = Pubkey::new_unique();
+        assert!(require_independent_investor(&creator, &investor).is_ok());
+        assert_eq!(
+            require_independent_investor(&creator, &creator),
+            Err(error!(FundingError::CreatorCannotInvest))
+        );
+    }
+}
@@ -1,5 +1,43 @@
+#![allow(clippy::result_large_err)]
+
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Token};
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::{self, Mint, Token, TokenAccount};
+
+pub mod access_control;
+pub mod escrow_instructions;
+pub mod exchange_instructions;
+pub mod exchange_standards;
+pub mod funding_instructions;
+pub mod funding_standards;
+pub mod multisig_instructions;
+pub mod multisig_standards;
+pub mod platform_instructions;
+pub mod platform_standards;
+pub mod resource_instructions;
+pub mod timelock_instructions;
+pub mod timelock_standards;
+pub mod token_instructions;
+pub mod token_standards;
+
+use access_control::*;
+use escrow_instructions::*;
+use exchange_instructions::*;
+use exchange_standards::{AuctionKind, ExchangeError, Ruling};
+use funding_instructions::*;
+use multisig_instructions::*;
+use multisig_standards::TransactionAccount;
+use platform_instructions::*;
+use platform_standards::{pay_with_fee, FeeCollected, PlatformError};
+use resource_instructions::*;
+use timelock_instructions::*;
+use timelock_standards::TimelockAction;
+use token_instructions::*;
+use token_standards::*;
+
+// Maximum lengths for resource strings
+pub const MAX_RESOURCE_TITLE_LEN: usize = 96;
+pub const MAX_RESOURCE_DESCRIPTION_LEN: usize = 496;
 
 declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
 
@@ -23,11 +61,48 @@ pub mod neplus {
         project.token_supply = token_supply;
         project.status = ProjectStatus::Active;
         project.created_at = Clock::get()?.unix_timestamp;
+        project.pending_creator = None;
+
+        Ok(())
+    }
+
+    // Propose a new project creator, or cancel a pending proposal with None
+    pub fn transfer_project_ownership(
+        ctx: Context<TransferProjectOwnership>,
+        new_creator: Option<Pubkey>,
+    ) -> Result<()> {
+        let project = &mut ctx.accounts.project;
+
+        match new_creator {
+            Some(new_creator) => project.start_ownership_transfer(new_creator)?,
+            None => project.pending_creator = None,
+        }
+
+        emit!(ProjectOwnershipTransferStarted {
+            project: project.key(),
+            creator: project.creator,
+            pending_creator: project.pending_creator,
+        });
 
         Ok(())
     }
 
-    // Add a collaborator to a project
+    // The proposed creator takes over the project
+    pub fn accept_project_ownership(ctx: Context<AcceptProjectOwnership>) -> Result<()> {
+        let project = &mut ctx.accounts.project;
+        let new_creator = ctx.accounts.new_creator.key();
+        let previous_creator = project.accept_ownership(new_creator)?;
+
+        emit!(ProjectOwnershipTransferred {
+            project: project.key(),
+            previous_creator,
+            new_creator,
+        });
+
+        Ok(())
+    }
+
+    // Request to join a project as a collaborator
     pub fn add_collaborator(
         ctx: Context<AddCollaborator>,
         role: String,
@@ -37,16 +112,125 @@ pub mod neplus {
         let project = &ctx.accounts.project;
         let collaborator = &ctx.accounts.collaborator;
 
+        if role.len() > MAX_ROLE_LEN {
+            return Err(error!(TokenError::MetadataTooLong));
+        }
+
         collaboration.project = project.key();
         collaboration.collaborator = collaborator.key();
         collaboration.role = role;
         collaboration.token_allocation = allocation;
-        collaboration.status = CollaborationStatus::Active;
+        collaboration.status = CollaborationStatus::Pending;
         collaboration.joined_at = Clock::get()?.unix_timestamp;
 
         Ok(())
     }
 
+    // Approve a pending collaboration and issue its soulbound credential
+    pub fn approve_collaboration(ctx: Context<ApproveCollaboration>) -> Result<()> {
+        let collaboration = &mut ctx.accounts.collaboration;
+        let credential = &mut ctx.accounts.credential;
+
+        if collaboration.status != CollaborationStatus::Pending {
+            return Err(error!(CollaborationError::InvalidCollaborationStatus));
+        }
+
+        let now = Clock::get()?.unix_timestamp;
+        collaboration.status = CollaborationStatus::Active;
+        collaboration.joined_at = now;
+
+        credential.project = collaboration.project;
+        credential.collaboration = collaboration.key();
+        credential.holder = collaboration.collaborator;
+        credential.mint = ctx.accounts.credential_mint.key();
+        credential.role = collaboration.role.clone();
+        credential.issued_at = now;
+        credential.revoked_at = None;
+        credential.bump = *ctx.bumps.get("credential").unwrap();
+
+        let collaboration_key = collaboration.key();
+        let seeds = &[b"credential", collaboration_key.as_ref(), &[credential.bump]];
+        let signer = &[&seeds[..]];
+
+        // Issue the credential and freeze it in the collaborator's wallet
+        token::mint_to(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::MintTo {
+                    mint: ctx.accounts.credential_mint.to_account_info(),
+                    to: ctx.accounts.holder_token_account.to_account_info(),
+                    authority: credential.to_account_info(),
+                },
+                signer,
+            ),
+            1,
+        )?;
+
+        token::freeze_account(CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            token::FreezeAccount {
+                account: ctx.accounts.holder_token_account.to_account_info(),
+                mint: ctx.accounts.credential_mint.to_account_info(),
+                authority: credential.to_account_info(),
+            },
+            signer,
+        ))?;
+
+        Ok(())
+    }
+
+    // End an active collaboration and revoke its credential. When the
+    // collaborator ends it themselves the credential token is also burned.
+    pub fn end_collaboration(ctx: Context<EndCollaboration>) -> Result<()> {
+        let collaboration = &mut ctx.accounts.collaboration;
+        let credential = &mut ctx.accounts.credential;
+        let authority = &ctx.accounts.authority;
+
+        if collaboration.status != CollaborationStatus::Active {
+            return Err(error!(CollaborationError::InvalidCollaborationStatus));
+        }
+        if authority.key() != ctx.accounts.project.creator
+            && authority.key() != collaboration.collaborator
+        {
+            return Err(error!(CollaborationError::UnauthorizedCollaborationAction));
+        }
+
+        collaboration.status = CollaborationStatus::Ended;
+        credential.revoked_at = Some(Clock::get()?.unix_timestamp);
+
+        if authority.key() == collaboration.collaborator
+            && ctx.accounts.holder_token_account.amount > 0
+        {
+            let collaboration_key = collaboration.key();
+            let seeds = &[b"credential", collaboration_key.as_ref(), &[credential.bump]];
+            let signer = &[&seeds[..]];
+
+            token::thaw_account(CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::ThawAccount {
+                    account: ctx.accounts.holder_token_account.to_account_info(),
+                    mint: ctx.accounts.credential_mint.to_account_info(),
+                    authority: credential.to_account_info(),
+                },
+                signer,
+            ))?;
+
+            token::burn(
+                CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    token::Burn {
+                        mint: ctx.accounts.credential_mint.to_account_info(),
+                        from: ctx.accounts.holder_token_account.to_account_info(),
+                        authority: authority.to_account_info(),
+                    },
+                ),
+                1,
+            )?;
+        }
+
+        Ok(())
+    }
+
     // Create a resource offering
     pub fn create_resource(
         ctx: Context<CreateResource>,
@@ -55,10 +239,18 @@ pub mod neplus {
         resource_type: ResourceType,
         exchange_type: ExchangeType,
         price: u64,
+        quantity: u64,
     ) -> Result<()> {
         let resource = &mut ctx.accounts.resource;
         let provider = &ctx.accounts.provider;
 
+        if title.len() > MAX_RESOURCE_TITLE_LEN || description.len() > MAX_RESOURCE_DESCRIPTION_LEN {
+            return Err(error!(ResourceError::FieldTooLong));
+        }
+        if quantity == 0 {
+            return Err(error!(ResourceError::InvalidQuantity));
+        }
+
         resource.provider = provider.key();
         resource.title = title;
         resource.description = description;
@@ -67,6 +259,11 @@ pub mod neplus {
         resource.price = price;
         resource.status = ResourceStatus::Available;
         resource.created_at = Clock::get()?.unix_timestamp;
+        resource.quantity_total = quantity;
+        resource.quantity_remaining = quantity;
+        resource.purchase_count = 0;
+        resource.payment_mint = ctx.accounts.payment_mint.key();
+        resource.open_commitments = 0;
 
         Ok(())
     }
@@ -74,56 +271,769 @@ pub mod neplus {
     // Exchange tokens for resources
     pub fn exchange_resource(
         ctx: Context<ExchangeResource>,
-        amount: u64,
+        quantity: u64,
     ) -> Result<()> {
         let resource = &mut ctx.accounts.resource;
+        let purchase = &mut ctx.accounts.purchase;
         let buyer = &ctx.accounts.buyer;
-        let provider = &ctx.accounts.provider;
 
-        // Transfer tokens from buyer to provider
-        token::transfer(
-            CpiContext::new(
-                ctx.accounts.token_program.to_account_info(),
-                token::Transfer {
-                    from: ctx.accounts.buyer_token_account.to_account_info(),
-                    to: ctx.accounts.provider_token_account.to_account_info(),
-                    authority: buyer.to_account_info(),
-                },
-            ),
-            amount,
+        if resource.exchange_type != ExchangeType::Token {
+            return Err(error!(ExchangeError::WrongExchangeType));
+        }
+        if resource.status != ResourceStatus::Available {
+            return Err(error!(ResourceError::ResourceUnavailable));
+        }
+        if quantity == 0 || quantity > resource.quantity_remaining {
+            return Err(error!(ResourceError::InvalidQuantity));
+        }
+
+        let total_price = resource.total_price(quantity)?;
+        let exempt = ctx.accounts.platform.platform_config
+            .is_fee_exempt(&ctx.accounts.access_control, &resource.provider);
+
+        // Transfer tokens from buyer to provider in the listed currency,
+        // less the platform fee
+        let fee = pay_with_fee(
+            &ctx.accounts.platform.platform_config,
+            exempt,
+            &ctx.accounts.token_program.to_account_info(),
+            &ctx.accounts.buyer_token_account.to_account_info(),
+            &ctx.accounts.provider_token_account.to_account_info(),
+            &ctx.accounts.treasury_token_account.to_account_info(),
+            &buyer.to_account_info(),
+            &[],
+            total_price,
         )?;
 
-        // Update resource status
-        resource.status = ResourceStatus::Exchanged;
+        if fee > 0 {
+            emit!(FeeCollected {
+                mint: resource.payment_mint,
+                payee: resource.provider,
+                gross: total_price,
+                fee,
+            });
+        }
+
+        // Record the purchase receipt
+        purchase.resource = resource.key();
+        purchase.buyer = buyer.key();
+        purchase.payment_mint = resource.payment_mint;
+        purchase.quantity = quantity;
+        purchase.unit_price = resource.price;
+        purchase.total_price = total_price;
+        purchase.purchased_at = Clock::get()?.unix_timestamp;
+        purchase.bump = *ctx.bumps.get("purchase").unwrap();
+
+        // Update remaining quantity and status
+        resource.quantity_remaining -= quantity;
+        resource.purchase_count = resource.purchase_count.checked_add(1)
+            .ok_or(ResourceError::PriceOverflow)?;
+        if resource.quantity_remaining == 0 {
+            resource.status = ResourceStatus::Exchanged;
+        }
 
         Ok(())
     }
+
+    // Update the listing details of a resource
+    pub fn update_resource(
+        ctx: Context<ModifyResource>,
+        description: Option<String>,
+        resource_type: Option<ResourceType>,
+        price: Option<u64>,
+    ) -> Result<()> {
+        resource_instructions::handle_update_resource(ctx, description, resource_type, price)
+    }
+
+    // Temporarily withdraw a resource from the market
+    pub fn delist_resource(ctx: Context<ModifyResource>) -> Result<()> {
+        resource_instructions::handle_delist_resource(ctx)
+    }
+
+    // Put a delisted resource back on the market
+    pub fn relist_resource(ctx: Context<ModifyResource>) -> Result<()> {
+        resource_instructions::handle_relist_resource(ctx)
+    }
+
+    // Close a finished or delisted resource and reclaim its rent
+    pub fn close_resource(ctx: Context<CloseResource>) -> Result<()> {
+        resource_instructions::handle_close_resource(ctx)
+    }
+
+    // Offer a collaboration on the buyer's project in exchange for a resource
+    pub fn make_barter_offer(
+        ctx: Context<MakeBarterOffer>,
+        role: String,
+        token_allocation: u64,
+        quantity: u64,
+    ) -> Result<()> {
+        exchange_instructions::handle_make_barter_offer(ctx, role, token_allocation, quantity)
+    }
+
+    // Provider accepts a barter offer and joins the buyer's project
+    pub fn accept_barter_offer(ctx: Context<AcceptBarterOffer>) -> Result<()> {
+        exchange_instructions::handle_accept_barter_offer(ctx)
+    }
+
+    // Mark the resource exchanged once the barter collaboration is active
+    pub fn settle_barter(ctx: Context<SettleBarter>) -> Result<()> {
+        exchange_instructions::handle_settle_barter(ctx)
+    }
+
+    // Withdraw an open barter offer or unwind an unapproved one
+    pub fn cancel_barter(ctx: Context<CancelBarter>) -> Result<()> {
+        exchange_instructions::handle_cancel_barter(ctx)
+    }
+
+    // Register a mint in which a project distributes revenue
+    pub fn open_revenue_ledger(ctx: Context<OpenRevenueLedger>) -> Result<()> {
+        exchange_instructions::handle_open_revenue_ledger(ctx)
+    }
+
+    // Pay for a resource with a capped share of future project revenue
+    pub fn exchange_future_benefit(
+        ctx: Context<ExchangeFutureBenefit>,
+        share_bps: u16,
+        cap: u64,
+        expires_at: i64,
+        quantity: u64,
+    ) -> Result<()> {
+        exchange_instructions::handle_exchange_future_benefit(ctx, share_bps, cap, expires_at, quantity)
+    }
+
+    // Deposit project revenue and honor outstanding future benefit agreements
+    pub fn distribute_project_revenue<'info>(
+        ctx: Context<'_, '_, '_, 'info, DistributeProjectRevenue<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        exchange_instructions::handle_distribute_project_revenue(ctx, amount)
+    }
+
+    // Propose a price and quantity for a resource, escrowing the funds
+    pub fn make_offer(
+        ctx: Context<MakeOffer>,
+        price: u64,
+        quantity: u64,
+        expires_at: i64,
+    ) -> Result<()> {
+        exchange_instructions::handle_make_offer(ctx, price, quantity, expires_at)
+    }
+
+    // Provider answers an offer with new terms
+    pub fn counter_offer(
+        ctx: Context<CounterOffer>,
+        price: u64,
+        quantity: u64,
+        expires_at: i64,
+    ) -> Result<()> {
+        exchange_instructions::handle_counter_offer(ctx, price, quantity, expires_at)
+    }
+
+    // Settle an offer at the agreed terms
+    pub fn accept_offer(ctx: Context<AcceptOffer>) -> Result<()> {
+        exchange_instructions::handle_accept_offer(ctx)
+    }
+
+    // Buyer withdraws an offer and recovers the escrow
+    pub fn withdraw_offer(ctx: Context<CloseOffer>) -> Result<()> {
+        exchange_instructions::handle_withdraw_offer(ctx)
+    }
+
+    // Provider rejects an offer, refunding the buyer
+    pub fn reject_offer(ctx: Context<CloseOffer>) -> Result<()> {
+        exchange_instructions::handle_reject_offer(ctx)
+    }
+
+    // Put a material or funding resource up for auction
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_auction(
+        ctx: Context<CreateAuction>,
+        kind: AuctionKind,
+        start_price: u64,
+        reserve_price: u64,
+        min_increment: u64,
+        start_time: i64,
+        end_time: i64,
+        extension_window: i64,
+    ) -> Result<()> {
+        exchange_instructions::handle_create_auction(
+            ctx,
+            kind,
+            start_price,
+            reserve_price,
+            min_increment,
+            start_time,
+            end_time,
+            extension_window,
+        )
+    }
+
+    // Bid on an auction
+    pub fn place_bid(ctx: Context<PlaceBid>, amount: u64) -> Result<()> {
+        exchange_instructions::handle_place_bid(ctx, amount)
+    }
+
+    // Pay out an ended auction; callable by anyone
+    pub fn settle_auction(ctx: Context<SettleAuction>) -> Result<()> {
+        exchange_instructions::handle_settle_auction(ctx)
+    }
+
+    // Buy resource units with payment held in escrow
+    pub fn escrow_purchase(
+        ctx: Context<EscrowPurchase>,
+        quantity: u64,
+        inspection_period: i64,
+    ) -> Result<()> {
+        escrow_instructions::handle_escrow_purchase(ctx, quantity, inspection_period)
+    }
+
+    // Release escrowed payment to the provider
+    pub fn release_escrow(ctx: Context<ReleaseEscrow>) -> Result<()> {
+        escrow_instructions::handle_release_escrow(ctx)
+    }
+
+    // Freeze an escrow and open a dispute
+    pub fn open_dispute(
+        ctx: Context<OpenDispute>,
+        evidence_uri: String,
+        evidence_hash: [u8; 32],
+    ) -> Result<()> {
+        escrow_instructions::handle_open_dispute(ctx, evidence_uri, evidence_hash)
+    }
+
+    // Answer a dispute with counter-evidence
+    pub fn respond_dispute(
+        ctx: Context<RespondDispute>,
+        evidence_uri: String,
+        evidence_hash: [u8; 32],
+    ) -> Result<()> {
+        escrow_instructions::handle_respond_dispute(ctx, evidence_uri, evidence_hash)
+    }
+
+    // Rule on a dispute as arbiter or governance
+    pub fn resolve_dispute(ctx: Context<SettleDispute>, ruling: Ruling) -> Result<()> {
+        escrow_instructions::handle_resolve_dispute(ctx, ruling)
+    }
+
+    // Apply the default outcome of a dispute after a missed deadline
+    pub fn finalize_dispute(ctx: Context<SettleDispute>) -> Result<()> {
+        escrow_instructions::handle_finalize_dispute(ctx)
+    }
+
+    // Create the platform role registry
+    pub fn initialize_access_control(ctx: Context<InitializeAccessControl>) -> Result<()> {
+        access_control::handle_initialize_access_control(ctx)
+    }
+
+    // Grant a platform role
+    pub fn grant_role(ctx: Context<ManageRole>, user: Pubkey, role: Role) -> Result<()> {
+        access_control::handle_grant_role(ctx, user, role)
+    }
+
+    // Revoke a platform role
+    pub fn revoke_role(ctx: Context<ManageRole>, user: Pubkey, role: Role) -> Result<()> {
+        access_control::handle_revoke_role(ctx, user, role)
+    }
+
+    // Publish or clear a project's Merkle investor allowlist
+    pub fn set_investor_root(
+        ctx: Context<SetInvestorRoot>,
+        merkle_root: Option<[u8; 32]>,
+    ) -> Result<()> {
+        access_control::handle_set_investor_root(ctx, merkle_root)
+    }
+
+    // Approve a wallet to invest in a project
+    pub fn grant_investor(ctx: Context<GrantInvestor>, investor: Pubkey) -> Result<()> {
+        access_control::handle_grant_investor(ctx, investor)
+    }
+
+    // Withdraw a project investor approval
+    pub fn revoke_investor(ctx: Context<RevokeInvestor>) -> Result<()> {
+        access_control::handle_revoke_investor(ctx)
+    }
+
+    // Prove membership of a project's Merkle investor allowlist
+    pub fn verify_investor(ctx: Context<VerifyInvestor>, proof: Vec<[u8; 32]>) -> Result<()> {
+        access_control::handle_verify_investor(ctx, proof)
+    }
+
+    // Create the global platform configuration
+    pub fn initialize_platform_config(
+        ctx: Context<InitializePlatformConfig>,
+        fee_bps: u16,
+        fee_exempt_roles: Vec<Role>,
+    ) -> Result<()> {
+        platform_instructions::handle_initialize_platform_config(ctx, fee_bps, fee_exempt_roles)
+    }
+
+    // Change the platform fee or exemptions
+    pub fn update_fee_config(
+        ctx: Context<UpdatePlatformConfig>,
+        fee_bps: u16,
+        fee_exempt_roles: Vec<Role>,
+    ) -> Result<()> {
+        platform_instructions::handle_update_fee_config(ctx, fee_bps, fee_exempt_roles)
+    }
+
+    // Register or remove the treasury token account for a payment mint
+    pub fn set_treasury(
+        ctx: Context<UpdatePlatformConfig>,
+        mint: Pubkey,
+        treasury: Option<Pubkey>,
+    ) -> Result<()> {
+        platform_instructions::handle_set_treasury(ctx, mint, treasury)
+    }
+
+    // Pause the whole platform or individual subsystems
+    pub fn pause(ctx: Context<UpdatePlatformConfig>, global: bool, subsystems: u8) -> Result<()> {
+        platform_instructions::handle_pause(ctx, global, subsystems)
+    }
+
+    // Queue an unpause that takes effect after the unpause delay
+    pub fn queue_unpause(
+        ctx: Context<UpdatePlatformConfig>,
+        global: bool,
+        subsystems: u8,
+    ) -> Result<()> {
+        platform_instructions::handle_queue_unpause(ctx, global, subsystems)
+    }
+
+    // Lift a pause, immediately for governance or once a queued unpause is due
+    pub fn unpause(ctx: Context<UpdatePlatformConfig>, global: bool, subsystems: u8) -> Result<()> {
+        platform_instructions::handle_unpause(ctx, global, subsystems)
+    }
+
+    // Create the timelock queue; role and fee changes go through it from now on
+    pub fn initialize_timelock(ctx: Context<InitializeTimelock>, delay: i64) -> Result<()> {
+        timelock_instructions::handle_initialize_timelock(ctx, delay)
+    }
+
+    // Queue a sensitive configuration change behind the timelock delay
+    pub fn queue_action(ctx: Context<QueueAction>, action: TimelockAction) -> Result<()> {
+        timelock_instructions::handle_queue_action(ctx, action)
+    }
+
+    // Cancel a queued action
+    pub fn cancel_action(ctx: Context<CancelAction>) -> Result<()> {
+        timelock_instructions::handle_cancel_action(ctx)
+    }
+
+    // Apply a queued action whose delay has passed
+    pub fn execute_action(ctx: Context<ExecuteAction>) -> Result<()> {
+        timelock_instructions::handle_execute_action(ctx)
+    }
+
+    // Create a multisig that can act as a project or token authority
+    pub fn create_multisig(
+        ctx: Context<CreateMultisig>,
+        members: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        multisig_instructions::handle_create_multisig(ctx, members, threshold)
+    }
+
+    // Propose a whitelisted instruction for the multisig to sign
+    pub fn propose_multisig_transaction(
+        ctx: Context<ProposeMultisigTransaction>,
+        accounts: Vec<TransactionAccount>,
+        data: Vec<u8>,
+    ) -> Result<()> {
+        multisig_instructions::handle_propose_multisig_transaction(ctx, accounts, data)
+    }
+
+    // Approve a proposed multisig transaction
+    pub fn approve_multisig_transaction(ctx: Context<ApproveMultisigTransaction>) -> Result<()> {
+        multisig_instructions::handle_approve_multisig_transaction(ctx)
+    }
+
+    // Execute an approved multisig transaction with the multisig as signer
+    pub fn execute_multisig_transaction<'info>(
+        ctx: Context<'_, '_, '_, 'info, ExecuteMultisigTransaction<'info>>,
+    ) -> Result<()> {
+        multisig_instructions::handle_execute_multisig_transaction(ctx)
+    }
+
+    // Change multisig members and threshold, through the multisig itself
+    pub fn change_multisig(
+        ctx: Context<ChangeMultisig>,
+        members: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        multisig_instructions::handle_change_multisig(ctx, members, threshold)
+    }
+
+    // Initialize a new platform token
+    pub fn initialize_token(
+        ctx: Context<InitializeToken>,
+        metadata: TokenMetadata,
+        config: TokenConfig,
+        distribution_rules: DistributionRules,
+        permissions: TokenPermissions,
+    ) -> Result<()> {
+        token_instructions::handle_initialize_token(
+            ctx,
+            metadata,
+            config,
+            distribution_rules,
+            permissions,
+        )
+    }
+
+    // Mint tokens to a holder
+    pub fn mint_token(ctx: Context<MintToken>, amount: u64) -> Result<()> {
+        token_instructions::handle_mint_token(ctx, amount)
+    }
+
+    // Burn tokens from a holder
+    pub fn burn_token(ctx: Context<BurnToken>, amount: u64) -> Result<()> {
+        token_instructions::handle_burn_token(ctx, amount)
+    }
+
+    // Transfer tokens between holders
+    pub fn transfer_token(ctx: Context<TransferToken>, amount: u64) -> Result<()> {
+        token_instructions::handle_transfer_token(ctx, amount)
+    }
+
+    // Authorize a holder to move a restricted token
+    pub fn issue_transfer_authorization(
+        ctx: Context<IssueTransferAuthorization>,
+        holder: Pubkey,
+        expires_at: Option<i64>,
+        period_limit: Option<u64>,
+        period_length: i64,
+    ) -> Result<()> {
+        token_instructions::handle_issue_transfer_authorization(
+            ctx,
+            holder,
+            expires_at,
+            period_limit,
+            period_length,
+        )
+    }
+
+    // Withdraw a holder's transfer authorization
+    pub fn revoke_transfer_authorization(ctx: Context<RevokeTransferAuthorization>) -> Result<()> {
+        token_instructions::handle_revoke_transfer_authorization(ctx)
+    }
+
+    // Set a holder's vesting schedule
+    pub fn update_vesting(ctx: Context<UpdateVesting>, schedule: VestingSchedule) -> Result<()> {
+        token_instructions::handle_update_vesting(ctx, schedule)
+    }
+
+    // Unlock the signer's vested tokens
+    pub fn release_vested(ctx: Context<ReleaseVested>) -> Result<()> {
+        token_instructions::handle_release_vested(ctx)
+    }
+
+    // Open a holder ledger entry for the signer
+    pub fn open_holder(ctx: Context<OpenHolder>) -> Result<()> {
+        token_instructions::handle_open_holder(ctx)
+    }
+
+    // Close an empty holder and reclaim its rent
+    pub fn close_holder(ctx: Context<CloseHolder>) -> Result<()> {
+        token_instructions::handle_close_holder(ctx)
+    }
+
+    // Audit a holder against its SPL token account, optionally resyncing
+    pub fn reconcile_holder(ctx: Context<ReconcileHolder>, resync: bool) -> Result<()> {
+        token_instructions::handle_reconcile_holder(ctx, resync)
+    }
+
+    // Audit total supply against the SPL mint, optionally resyncing
+    pub fn audit_supply(ctx: Context<AuditSupply>, resync: bool) -> Result<()> {
+        token_instructions::handle_audit_supply(ctx, resync)
+    }
+
+    // Create the NFT collection of a project
+    pub fn create_collection(
+        ctx: Context<CreateCollection>,
+        name: String,
+        symbol: String,
+        uri: String,
+    ) -> Result<()> {
+        token_instructions::handle_create_collection(ctx, name, symbol, uri)
+    }
+
+    // Mint a unique item into a project collection
+    pub fn mint_nft(ctx: Context<MintNft>, uri: String) -> Result<()> {
+        token_instructions::handle_mint_nft(ctx, uri)
+    }
+
+    // Mint ownership tokens representing a resource
+    pub fn tokenize_resource(
+        ctx: Context<TokenizeResource>,
+        token_type: TokenType,
+        shares: u64,
+        decimals: u8,
+        royalty: Option<RoyaltyConfig>,
+    ) -> Result<()> {
+        token_instructions::handle_tokenize_resource(ctx, token_type, shares, decimals, royalty)
+    }
+
+    // List project tokens for sale at a fixed price
+    pub fn list_tokens(ctx: Context<ListTokens>, amount: u64, price: u64) -> Result<()> {
+        token_instructions::handle_list_tokens(ctx, amount, price)
+    }
+
+    // List resource tokens for sale at a fixed price
+    pub fn list_resource_tokens(
+        ctx: Context<ListResourceTokens>,
+        amount: u64,
+        price: u64,
+    ) -> Result<()> {
+        token_instructions::handle_list_resource_tokens(ctx, amount, price)
+    }
+
+    // Buy listed project tokens, paying any creator royalty
+    pub fn buy_listing(ctx: Context<BuyListing>, amount: u64) -> Result<()> {
+        token_instructions::handle_buy_listing(ctx, amount)
+    }
+
+    // Buy listed resource tokens, paying any creator royalty
+    pub fn buy_resource_listing(ctx: Context<BuyResourceListing>, amount: u64) -> Result<()> {
+        token_instructions::handle_buy_resource_listing(ctx, amount)
+    }
+
+    // Withdraw a listing and its delegation over the unsold tokens
+    pub fn cancel_listing(ctx: Context<CancelListing>) -> Result<()> {
+        token_instructions::handle_cancel_listing(ctx)
+    }
+
+    // Propose a new token authority, or cancel a pending proposal
+    pub fn transfer_token_authority(
+        ctx: Context<TransferTokenAuthority>,
+        new_authority: Option<Pubkey>,
+    ) -> Result<()> {
+        token_instructions::handle_transfer_token_authority(ctx, new_authority)
+    }
+
+    // Take over a token as its proposed authority
+    pub fn accept_token_authority(ctx: Context<AcceptTokenAuthority>) -> Result<()> {
+        token_instructions::handle_accept_token_authority(ctx)
+    }
+
+    // Permanently give up a token's authority
+    pub fn renounce_token_authority(ctx: Context<RenounceTokenAuthority>) -> Result<()> {
+        token_instructions::handle_renounce_token_authority(ctx)
+    }
+
+    // Burn resource tokens to claim the underlying resource
+    pub fn redeem_resource(ctx: Context<RedeemResource>, shares: u64) -> Result<()> {
+        token_instructions::handle_redeem_resource(ctx, shares)
+    }
+
+    // Open a milestone-based funding pool for a project
+    pub fn create_funding(
+        ctx: Context<CreateFunding>,
+        reviewer: Option<Pubkey>,
+        approval_threshold_bps: u16,
+    ) -> Result<()> {
+        funding_instructions::handle_create_funding(ctx, reviewer, approval_threshold_bps)
+    }
+
+    // Add a funding tranche before any deposits are made
+    pub fn add_milestone(
+        ctx: Context<AddMilestone>,
+        amount: u64,
+        deadline: i64,
+        uri: String,
+    ) -> Result<()> {
+        funding_instructions::handle_add_milestone(ctx, amount, deadline, uri)
+    }
+
+    // Deposit into a project's funding vault
+    pub fn deposit_funds(ctx: Context<DepositFunds>, amount: u64) -> Result<()> {
+        funding_instructions::handle_deposit_funds(ctx, amount)
+    }
+
+    // Approve a milestone as the designated reviewer
+    pub fn approve_milestone(ctx: Context<ApproveMilestone>) -> Result<()> {
+        funding_instructions::handle_approve_milestone(ctx)
+    }
+
+    // Vote to approve a milestone, weighted by deposit
+    pub fn vote_milestone(ctx: Context<VoteMilestone>) -> Result<()> {
+        funding_instructions::handle_vote_milestone(ctx)
+    }
+
+    // Release an approved tranche to the project creator
+    pub fn release_milestone(ctx: Context<ReleaseMilestone>) -> Result<()> {
+        funding_instructions::handle_release_milestone(ctx)
+    }
+
+    // Fail the funding when a milestone deadline passes unapproved
+    pub fn mark_milestone_missed(ctx: Context<MarkMilestoneMissed>) -> Result<()> {
+        funding_instructions::handle_mark_milestone_missed(ctx)
+    }
+
+    // Reclaim a pro-rata share of a failed funding
+    pub fn reclaim_funds(ctx: Context<ReclaimFunds>) -> Result<()> {
+        funding_instructions::handle_reclaim_funds(ctx)
+    }
+
+    // Launch a crowdfunding campaign for a project
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_campaign(
+        ctx: Context<CreateCampaign>,
+        soft_cap: u64,
+        hard_cap: u64,
+        start_time: i64,
+        end_time: i64,
+        min_contribution: u64,
+        max_contribution: u64,
+        token_rate: u64,
+        investors_only: bool,
+    ) -> Result<()> {
+        funding_instructions::handle_create_campaign(
+            ctx,
+            soft_cap,
+            hard_cap,
+            start_time,
+            end_time,
+            min_contribution,
+            max_contribution,
+            token_rate,
+            investors_only,
+        )
+    }
+
+    // Contribute to an active campaign
+    pub fn contribute(ctx: Context<Contribute>, amount: u64) -> Result<()> {
+        funding_instructions::handle_contribute(ctx, amount)
+    }
+
+    // Settle a campaign that has ended or reached its hard cap
+    pub fn finalize_campaign(ctx: Context<FinalizeCampaign>) -> Result<()> {
+        funding_instructions::handle_finalize_campaign(ctx)
+    }
+
+    // Claim project tokens from a successful campaign
+    pub fn claim_campaign_tokens(ctx: Context<ClaimCampaignTokens>) -> Result<()> {
+        funding_instructions::handle_claim_campaign_tokens(ctx)
+    }
+
+    // Refund a contribution to a failed campaign
+    pub fn refund_contribution(ctx: Context<RefundContribution>) -> Result<()> {
+        funding_instructions::handle_refund_contribution(ctx)
+    }
 }
 
 #[derive(Accounts)]
 pub struct InitializeProject<'info> {
-    #[account(init, payer = creator, space = 8 + 32 + 100 + 500 + 8 + 1 + 8)]
+    #[account(init, payer = creator, space = 8 + 32 + 100 + 500 + 8 + 1 + 8 + 33)]
     pub project: Account<'info, Project>,
+    pub platform: ProjectsLive<'info>,
     #[account(mut)]
     pub creator: Signer<'info>,
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct TransferProjectOwnership<'info> {
+    #[account(mut, has_one = creator)]
+    pub project: Account<'info, Project>,
+    pub platform: ProjectsLive<'info>,
+    pub creator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptProjectOwnership<'info> {
+    #[account(mut)]
+    pub project: Account<'info, Project>,
+    pub platform: ProjectsLive<'info>,
+    pub new_creator: Signer<'info>,
+}
+
 #[derive(Accounts)]
 pub struct AddCollaborator<'info> {
-    #[account(init, payer = collaborator, space = 8 + 32 + 32 + 100 + 8 + 1 + 8)]
+    #[account(init, payer = collaborator, space = Collaboration::LEN)]
     pub collaboration: Account<'info, Collaboration>,
     #[account(mut)]
     pub project: Account<'info, Project>,
+    pub platform: ProjectsLive<'info>,
     #[account(mut)]
     pub collaborator: Signer<'info>,
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct ApproveCollaboration<'info> {
+    #[account(mut, has_one = project)]
+    pub collaboration: Account<'info, Collaboration>,
+    #[account(has_one = creator)]
+    pub project: Account<'info, Project>,
+    #[account(
+        init,
+        payer = creator,
+        space = Credential::LEN,
+        seeds = [b"credential", collaboration.key().as_ref()],
+        bump
+    )]
+    pub credential: Account<'info, Credential>,
+    #[account(
+        init,
+        payer = creator,
+        seeds = [b"credential_mint", collaboration.key().as_ref()],
+        bump,
+        mint::decimals = 0,
+        mint::authority = credential,
+        mint::freeze_authority = credential
+    )]
+    pub credential_mint: Account<'info, Mint>,
+    /// CHECK: Collaborator wallet receiving the credential
+    #[account(address = collaboration.collaborator)]
+    pub collaborator: AccountInfo<'info>,
+    #[account(
+        init,
+        payer = creator,
+        associated_token::mint = credential_mint,
+        associated_token::authority = collaborator
+    )]
+    pub holder_token_account: Account<'info, TokenAccount>,
+    pub platform: ProjectsLive<'info>,
+    #[account(mut)]
+    pub creator: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct EndCollaboration<'info> {
+    #[account(mut, has_one = project)]
+    pub collaboration: Account<'info, Collaboration>,
+    pub project: Account<'info, Project>,
+    #[account(
+        mut,
+        has_one = collaboration,
+        seeds = [b"credential", collaboration.key().as_ref()],
+        bump = credential.bump
+    )]
+    pub credential: Account<'info, Credential>,
+    #[account(mut, address = credential.mint)]
+    pub credential_mint: Account<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = credential_mint,
+        associated_token::authority = credential.holder
+    )]
+    pub holder_token_account: Account<'info, TokenAccount>,
+    pub platform: ProjectsLive<'info>,
+    pub authority: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
 #[derive(Accounts)]
 pub struct CreateResource<'info> {
-    #[account(init, payer = provider, space = 8 + 32 + 100 + 500 + 1 + 1 + 8 + 1 + 8)]
+    #[account(init, payer = provider, space = 8 + 32 + 100 + 500 + 1 + 1 + 8 + 1 + 8 + 8 + 8 + 8 + 32 + 4)]
     pub resource: Account<'info, Resource>,
+    // Currency the resource is priced in, e.g. USDC or a project token
+    pub payment_mint: Account<'info, Mint>,
+    pub platform: ExchangeLive<'info>,
     #[account(mut)]
     pub provider: Signer<'info>,
     pub system_program: Program<'info, System>,
@@ -131,17 +1041,43 @@ pub struct CreateResource<'info> {
 
 #[derive(Accounts)]
 pub struct ExchangeResource<'info> {
-    #[account(mut)]
+    #[account(mut, has_one = provider)]
     pub resource: Account<'info, Resource>,
+    #[account(
+        init,
+        payer = buyer,
+        space = Purchase::LEN,
+        seeds = [
+            b"purchase",
+            resource.key().as_ref(),
+            buyer.key().as_ref(),
+            &resource.purchase_count.to_le_bytes(),
+        ],
+        bump
+    )]
+    pub purchase: Account<'info, Purchase>,
     #[account(mut)]
     pub buyer: Signer<'info>,
     /// CHECK: Provider account
     pub provider: AccountInfo<'info>,
-    #[account(mut)]
+    #[account(address = resource.payment_mint @ ResourceError::WrongPaymentMint)]
+    pub payment_mint: Account<'info, Mint>,
+    #[account(mut, token::mint = payment_mint)]
     pub buyer_token_account: Account<'info, token::TokenAccount>,
-    #[account(mut)]
+    #[account(mut, token::mint = payment_mint, token::authority = provider)]
     pub provider_token_account: Account<'info, token::TokenAccount>,
+    pub platform: ExchangeLive<'info>,
+    #[account(seeds = [b"access_control"], bump)]
+    pub access_control: Account<'info, AccessControl>,
+    #[account(
+        mut,
+        token::mint = payment_mint,
+        constraint = platform.platform_config.is_treasury(&payment_mint.key(), &treasury_token_account.key())
+            @ PlatformError::UnknownTreasury
+    )]
+    pub treasury_token_account: Account<'info, token::TokenAccount>,
     pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
 }
 
 #[account]
@@ -152,6 +1088,44 @@ pub struct Project {
     pub token_supply: u64,
     pub status: ProjectStatus,
     pub created_at: i64,
+    // Proposed new creator, awaiting acceptance
+    pub pending_creator: Option<Pubkey>,
+}
+
+impl Project {
+    pub fn start_ownership_transfer(&mut self, new_creator: Pubkey) -> Result<()> {
+        if new_creator == self.creator {
+            return Err(error!(ProjectError::InvalidNewOwner));
+        }
+        self.pending_creator = Some(new_creator);
+        Ok(())
+    }
+
+    // Returns the previous creator
+    pub fn accept_ownership(&mut self, new_creator: Pubkey) -> Result<Pubkey> {
+        if self.pending_creator != Some(new_creator) {
+            return Err(error!(ProjectError::NotPendingOwner));
+        }
+        let previous = self.creator;
+        self.creator = new_creator;
+        self.pending_creator = None;
+        Ok(previous)
+    }
+}
+
+#[event]
+pub struct ProjectOwnershipTransferStarted {
+    pub project: Pubkey,
+    pub creator: Pubkey,
+    // None when a pending transfer was cancelled
+    pub pending_creator: Option<Pubkey>,
+}
+
+#[event]
+pub struct ProjectOwnershipTransferred {
+    pub project: Pubkey,
+    pub previous_creator: Pubkey,
+    pub new_creator: Pubkey,
 }
 
 #[account]
@@ -164,6 +1138,10 @@ pub struct Collaboration {
     pub joined_at: i64,
 }
 
+impl Collaboration {
+    pub const LEN: usize = 8 + 32 + 32 + 100 + 8 + 1 + 8;
+}
+
 #[account]
 pub struct Resource {
     pub provider: Pubkey,
@@ -171,9 +1149,64 @@ pub struct Resource {
     pub description: String,
     pub resource_type: ResourceType,
     pub exchange_type: ExchangeType,
+    // Price per unit, in base units of `payment_mint`
     pub price: u64,
     pub status: ResourceStatus,
     pub created_at: i64,
+    pub quantity_total: u64,
+    pub quantity_remaining: u64,
+    pub purchase_count: u64,
+    pub payment_mint: Pubkey,
+    // Offers, barter offers and escrows still awaiting settlement
+    pub open_commitments: u32,
+}
+
+impl Resource {
+    // Cost of buying `quantity` units at the listed unit price
+    pub fn total_price(&self, quantity: u64) -> Result<u64> {
+        self.price.checked_mul(quantity)
+            .ok_or(error!(ResourceError::PriceOverflow))
+    }
+
+    // Track a counterparty whose offer or escrow references this resource
+    pub fn open_commitment(&mut self) -> Result<()> {
+        self.open_commitments = self.open_commitments.checked_add(1)
+            .ok_or(error!(ResourceError::CommitmentOverflow))?;
+        Ok(())
+    }
+
+    pub fn settle_commitment(&mut self) {
+        self.open_commitments = self.open_commitments.saturating_sub(1);
+    }
+
+    // Return units from a refunded purchase, reopening a sold-out resource
+    pub fn restock(&mut self, quantity: u64) -> Result<()> {
+        self.quantity_remaining = self.quantity_remaining.checked_add(quantity)
+            .filter(|remaining| *remaining <= self.quantity_total)
+            .ok_or(error!(ResourceError::InvalidQuantity))?;
+        if self.status == ResourceStatus::Exchanged {
+            self.status.ensure_transition(&ResourceStatus::Available)?;
+            self.status = ResourceStatus::Available;
+        }
+        Ok(())
+    }
+}
+
+// Receipt for a single purchase of resource units
+#[account]
+pub struct Purchase {
+    pub resource: Pubkey,
+    pub buyer: Pubkey,
+    pub payment_mint: Pubkey,
+    pub quantity: u64,
+    pub unit_price: u64,
+    pub total_price: u64,
+    pub purchased_at: i64,
+    pub bump: u8,
+}
+
+impl Purchase {
+    pub const LEN: usize = 8 + 32 + 32 + 32 + 8 + 8 + 8 + 8 + 1;
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq)]
@@ -187,6 +1220,7 @@ pub enum ProjectStatus {
 pub enum CollaborationStatus {
     Active,
     Ended,
+    Pending,
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq)]
@@ -209,4 +1243,192 @@ pub enum ResourceStatus {
     Available,
     Reserved,
     Exchanged,
-}
\ No newline at end of file
+    Tokenized,
+    Consumed,
+    Delisted,
+    Closed,
+}
+
+impl ResourceStatus {
+    // Lifecycle of a resource listing:
+    //
+    //   Available <-> Reserved -> Exchanged -> Closed
+    //   Exchanged -> Available, when a refund returns units
+    //   Available <-> Delisted -> Closed
+    //   Available -> Tokenized -> Consumed -> Closed
+    //   Available -> Closed
+    pub fn can_transition_to(&self, next: &ResourceStatus) -> bool {
+        use ResourceStatus::*;
+        matches!(
+            (self, next),
+            (Available, Reserved)
+                | (Available, Exchanged)
+                | (Available, Tokenized)
+                | (Available, Delisted)
+                | (Available, Closed)
+                | (Reserved, Available)
+                | (Reserved, Exchanged)
+                | (Delisted, Available)
+                | (Delisted, Closed)
+                | (Tokenized, Consumed)
+                | (Exchanged, Available)
+                | (Exchanged, Closed)
+                | (Consumed, Closed)
+        )
+    }
+
+    pub fn ensure_transition(&self, next: &ResourceStatus) -> Result<()> {
+        if self.can_transition_to(next) {
+            return Ok(());
+        }
+        if *self == ResourceStatus::Reserved {
+            return Err(error!(ResourceError::ResourceReserved));
+        }
+        Err(error!(ResourceError::InvalidStatusTransition))
+    }
+}
+
+#[error_code]
+pub enum CollaborationError {
+    #[msg("Collaboration is not in the required status")]
+    InvalidCollaborationStatus,
+    #[msg("Only the project creator or collaborator may do this")]
+    UnauthorizedCollaborationAction,
+}
+
+#[error_code]
+pub enum ProjectError {
+    #[msg("New owner must differ from the current owner")]
+    InvalidNewOwner,
+    #[msg("Signer is not the pending owner")]
+    NotPendingOwner,
+}
+
+#[error_code]
+pub enum ResourceError {
+    #[msg("Resource is not available for this operation")]
+    ResourceUnavailable,
+    #[msg("Resource is reserved and cannot be modified")]
+    ResourceReserved,
+    #[msg("Invalid resource status transition")]
+    InvalidStatusTransition,
+    #[msg("Resource field too long")]
+    FieldTooLong,
+    #[msg("Invalid or unavailable quantity")]
+    InvalidQuantity,
+    #[msg("Price calculation overflow")]
+    PriceOverflow,
+    #[msg("Payment must be made in the resource's payment mint")]
+    WrongPaymentMint,
+    #[msg("Resource has outstanding offers or escrows")]
+    OpenCommitments,
+    #[msg("Resource commitment count overflow")]
+    CommitmentOverflow,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resource_lifecycle_transitions() {
+        use ResourceStatus::*;
+        assert!(Available.can_transition_to(&Reserved));
+        assert!(Reserved.can_transition_to(&Available));
+        assert!(Delisted.can_transition_to(&Available));
+        assert!(Tokenized.can_transition_to(&Consumed));
+        assert!(Exchanged.can_transition_to(&Closed));
+
+        assert!(!Reserved.can_transition_to(&Closed));
+        assert!(!Reserved.can_transition_to(&Delisted));
+        assert!(!Tokenized.can_transition_to(&Closed));
+        assert!(!Closed.can_transition_to(&Available));
+        assert!(!Exchanged.can_transition_to(&Reserved));
+    }
+
+    #[test]
+    fn reserved_resources_report_reserved() {
+        assert_eq!(
+            ResourceStatus::Reserved.ensure_transition(&ResourceStatus::Closed),
+            Err(error!(ResourceError::ResourceReserved))
+        );
+        assert_eq!(
+            ResourceStatus::Closed.ensure_transition(&ResourceStatus::Available),
+            Err(error!(ResourceError::InvalidStatusTransition))
+        );
+    }
+
+    fn resource(open_commitments: u32) -> Resource {
+        Resource {
+            provider: Pubkey::default(),
+            title: String::new(),
+            description: String::new(),
+            resource_type: ResourceType::Service,
+            exchange_type: ExchangeType::Token,
+            price: 10,
+            status: ResourceStatus::Available,
+            created_at: 0,
+            quantity_total: 5,
+            quantity_remaining: 5,
+            purchase_count: 0,
+            payment_mint: Pubkey::default(),
+            open_commitments,
+        }
+    }
+
+    #[test]
+    fn commitments_are_counted() {
+        let mut open = resource(0);
+        open.open_commitment().unwrap();
+        open.open_commitment().unwrap();
+        open.settle_commitment();
+        assert_eq!(open.open_commitments, 1);
+
+        open.settle_commitment();
+        open.settle_commitment();
+        assert_eq!(open.open_commitments, 0);
+
+        let mut full = resource(u32::MAX);
+        assert_eq!(full.open_commitment(), Err(error!(ResourceError::CommitmentOverflow)));
+        assert_eq!(full.open_commitments, u32::MAX);
+    }
+
+    fn project(creator: Pubkey) -> Project {
+        Project {
+            creator,
+            title: String::new(),
+            description: String::new(),
+            token_supply: 0,
+            status: ProjectStatus::Active,
+            created_at: 0,
+            pending_creator: None,
+        }
+    }
+
+    #[test]
+    fn project_ownership_moves_in_two_steps() {
+        let creator = Pubkey::new_unique();
+        let new_creator = Pubkey::new_unique();
+        let mut project = project(creator);
+
+        assert!(project.start_ownership_transfer(creator).is_err());
+        project.start_ownership_transfer(new_creator).unwrap();
+        assert_eq!(project.creator, creator);
+        assert_eq!(project.pending_creator, Some(new_creator));
+
+        assert_eq!(project.accept_ownership(new_creator).unwrap(), creator);
+        assert_eq!(project.creator, new_creator);
+        assert_eq!(project.pending_creator, None);
+    }
+
+    #[test]
+    fn only_the_pending_owner_accepts() {
+        let creator = Pubkey::new_unique();
+        let mut project = project(creator);
+        assert!(project.accept_ownership(creator).is_err());
+
+        project.start_ownership_transfer(Pubkey::new_unique()).unwrap();
+        assert!(project.accept_ownership(Pubkey::new_unique()).is_err());
+        assert_eq!(project.creator, creator);
+    }
+}
@@ -0,0 +1,222 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program::invoke_signed;
+use crate::multisig_standards::*;
+use crate::platform_instructions::*;
+
+#[derive(Accounts)]
+pub struct CreateMultisig<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = Multisig::LEN,
+        seeds = [b"multisig", create_key.key().as_ref()],
+        bump
+    )]
+    pub multisig: Account<'info, Multisig>,
+    // Throwaway key that makes the multisig address unique
+    pub create_key: Signer<'info>,
+    pub platform: GovernanceLive<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeMultisigTransaction<'info> {
+    #[account(
+        mut,
+        seeds = [b"multisig", multisig.create_key.as_ref()],
+        bump = multisig.bump
+    )]
+    pub multisig: Account<'info, Multisig>,
+    #[account(
+        init,
+        payer = proposer,
+        space = MultisigTransaction::LEN,
+        seeds = [b"multisig_tx", multisig.key().as_ref(), &multisig.nonce.to_le_bytes()],
+        bump
+    )]
+    pub transaction: Account<'info, MultisigTransaction>,
+    pub platform: GovernanceLive<'info>,
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveMultisigTransaction<'info> {
+    #[account(seeds = [b"multisig", multisig.create_key.as_ref()], bump = multisig.bump)]
+    pub multisig: Account<'info, Multisig>,
+    #[account(
+        mut,
+        has_one = multisig,
+        seeds = [b"multisig_tx", multisig.key().as_ref(), &transaction.index.to_le_bytes()],
+        bump = transaction.bump
+    )]
+    pub transaction: Account<'info, MultisigTransaction>,
+    pub platform: GovernanceLive<'info>,
+    pub member: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteMultisigTransaction<'info> {
+    // Not `mut`: the executed instruction may rewrite the multisig itself
+    #[account(seeds = [b"multisig", multisig.create_key.as_ref()], bump = multisig.bump)]
+    pub multisig: Account<'info, Multisig>,
+    #[account(
+        mut,
+        has_one = multisig,
+        seeds = [b"multisig_tx", multisig.key().as_ref(), &transaction.index.to_le_bytes()],
+        bump = transaction.bump
+    )]
+    pub transaction: Account<'info, MultisigTransaction>,
+    pub platform: GovernanceLive<'info>,
+    pub executor: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ChangeMultisig<'info> {
+    // Only the multisig itself, through an executed transaction
+    #[account(
+        mut,
+        signer,
+        seeds = [b"multisig", multisig.create_key.as_ref()],
+        bump = multisig.bump
+    )]
+    pub multisig: Account<'info, Multisig>,
+    pub platform: GovernanceLive<'info>,
+}
+
+// Multisig instruction handlers
+pub fn handle_create_multisig(
+    ctx: Context<CreateMultisig>,
+    members: Vec<Pubkey>,
+    threshold: u8,
+) -> Result<()> {
+    validate_members(&members, threshold)?;
+
+    let multisig = &mut ctx.accounts.multisig;
+    multisig.create_key = ctx.accounts.create_key.key();
+    multisig.members = members;
+    multisig.threshold = threshold;
+    multisig.nonce = 0;
+    multisig.member_set_seqno = 0;
+    multisig.bump = *ctx.bumps.get("multisig").unwrap();
+
+    Ok(())
+}
+
+// A member proposes a whitelisted neplus instruction, approving it as they do
+pub fn handle_propose_multisig_transaction(
+    ctx: Context<ProposeMultisigTransaction>,
+    accounts: Vec<TransactionAccount>,
+    data: Vec<u8>,
+) -> Result<()> {
+    let multisig = &mut ctx.accounts.multisig;
+    let proposer = ctx.accounts.proposer.key();
+
+    let member_index = multisig.member_index(&proposer)
+        .ok_or(MultisigError::NotAMember)?;
+    if accounts.len() > MAX_TRANSACTION_ACCOUNTS || data.len() > MAX_TRANSACTION_DATA_LEN {
+        return Err(error!(MultisigError::TransactionTooLarge));
+    }
+    if !is_whitelisted(&data) {
+        return Err(error!(MultisigError::InstructionNotWhitelisted));
+    }
+
+    let transaction = &mut ctx.accounts.transaction;
+    transaction.multisig = multisig.key();
+    transaction.index = multisig.nonce;
+    transaction.proposer = proposer;
+    transaction.accounts = accounts;
+    transaction.data = data;
+    transaction.approvals = vec![false; multisig.members.len()];
+    transaction.approvals[member_index] = true;
+    transaction.member_set_seqno = multisig.member_set_seqno;
+    transaction.executed = false;
+    transaction.bump = *ctx.bumps.get("transaction").unwrap();
+
+    multisig.nonce = multisig.nonce.checked_add(1)
+        .ok_or(MultisigError::TransactionTooLarge)?;
+
+    emit!(MultisigTransactionProposed {
+        multisig: multisig.key(),
+        transaction: transaction.key(),
+        proposer,
+    });
+
+    Ok(())
+}
+
+pub fn handle_approve_multisig_transaction(ctx: Context<ApproveMultisigTransaction>) -> Result<()> {
+    let multisig = &ctx.accounts.multisig;
+    let transaction = &mut ctx.accounts.transaction;
+    let member = ctx.accounts.member.key();
+
+    transaction.approve(multisig, &member)?;
+
+    emit!(MultisigTransactionApproved {
+        transaction: transaction.key(),
+        member,
+        approvals: transaction.approval_count() as u8,
+    });
+
+    Ok(())
+}
+
+// Anyone executes a transaction that reached the threshold. The accounts of
+// the proposed instruction are passed as remaining accounts, along with the
+// neplus program.
+pub fn handle_execute_multisig_transaction<'info>(
+    ctx: Context<'_, '_, '_, 'info, ExecuteMultisigTransaction<'info>>,
+) -> Result<()> {
+    let multisig = &ctx.accounts.multisig;
+    let transaction = &ctx.accounts.transaction;
+
+    transaction.require_approved(multisig)?;
+
+    let multisig_key = multisig.key();
+    let instruction = Instruction {
+        program_id: crate::ID,
+        accounts: transaction.accounts.iter()
+            .map(|account| AccountMeta {
+                pubkey: account.pubkey,
+                is_signer: account.is_signer || account.pubkey == multisig_key,
+                is_writable: account.is_writable,
+            })
+            .collect(),
+        data: transaction.data.clone(),
+    };
+
+    let mut account_infos = ctx.remaining_accounts.to_vec();
+    account_infos.push(multisig.to_account_info());
+    let seeds = &[b"multisig", multisig.create_key.as_ref(), &[multisig.bump]];
+    invoke_signed(&instruction, &account_infos, &[&seeds[..]])?;
+
+    let transaction = &mut ctx.accounts.transaction;
+    transaction.executed = true;
+
+    emit!(MultisigTransactionExecuted {
+        transaction: transaction.key(),
+        executed_by: ctx.accounts.executor.key(),
+    });
+
+    Ok(())
+}
+
+// Replace the members and threshold; pending proposals become stale
+pub fn handle_change_multisig(
+    ctx: Context<ChangeMultisig>,
+    members: Vec<Pubkey>,
+    threshold: u8,
+) -> Result<()> {
+    validate_members(&members, threshold)?;
+
+    let multisig = &mut ctx.accounts.multisig;
+    multisig.members = members;
+    multisig.threshold = threshold;
+    multisig.member_set_seqno = multisig.member_set_seqno.wrapping_add(1);
+
+    Ok(())
+}
@@ -0,0 +1,294 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hash;
+
+pub const MAX_MULTISIG_MEMBERS: usize = 10;
+pub const MAX_TRANSACTION_ACCOUNTS: usize = 16;
+pub const MAX_TRANSACTION_DATA_LEN: usize = 256;
+
+// neplus instructions a multisig may execute. Instructions where the
+// authority also pays for new accounts are left out, since a PDA holding
+// data cannot fund account creation.
+pub const MULTISIG_INSTRUCTIONS: &[&str] = &[
+    "mint_token",
+    "burn_token",
+    "update_vesting",
+    "revoke_transfer_authorization",
+    "end_collaboration",
+    "revoke_investor",
+    "finalize_campaign",
+    "update_resource",
+    "delist_resource",
+    "relist_resource",
+    "change_multisig",
+    "transfer_project_ownership",
+    "accept_project_ownership",
+    "transfer_token_authority",
+    "accept_token_authority",
+    "renounce_token_authority",
+];
+
+// Shared authority, PDA at ["multisig", create_key]. Its address can be set
+// as a project creator or token authority; it signs by executing approved
+// transactions.
+#[account]
+pub struct Multisig {
+    pub create_key: Pubkey,
+    pub members: Vec<Pubkey>,
+    pub threshold: u8,
+    // Index of the next proposed transaction
+    pub nonce: u64,
+    // Bumped on membership changes, invalidating pending proposals
+    pub member_set_seqno: u32,
+    pub bump: u8,
+}
+
+impl Multisig {
+    pub const LEN: usize = 8 + 32 + (4 + MAX_MULTISIG_MEMBERS * 32) + 1 + 8 + 4 + 1;
+
+    pub fn member_index(&self, user: &Pubkey) -> Option<usize> {
+        self.members.iter().position(|member| member == user)
+    }
+}
+
+pub fn validate_members(members: &[Pubkey], threshold: u8) -> Result<()> {
+    if members.is_empty() || members.len() > MAX_MULTISIG_MEMBERS {
+        return Err(error!(MultisigError::InvalidMembers));
+    }
+    if members.iter().enumerate().any(|(i, member)| members[..i].contains(member)) {
+        return Err(error!(MultisigError::InvalidMembers));
+    }
+    if threshold == 0 || threshold as usize > members.len() {
+        return Err(error!(MultisigError::InvalidThreshold));
+    }
+    Ok(())
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct TransactionAccount {
+    pub pubkey: Pubkey,
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+impl TransactionAccount {
+    pub const LEN: usize = 32 + 1 + 1;
+}
+
+// Proposed neplus instruction, PDA at ["multisig_tx", multisig, index]
+#[account]
+pub struct MultisigTransaction {
+    pub multisig: Pubkey,
+    pub index: u64,
+    pub proposer: Pubkey,
+    pub accounts: Vec<TransactionAccount>,
+    pub data: Vec<u8>,
+    // One flag per multisig member, in member order
+    pub approvals: Vec<bool>,
+    pub member_set_seqno: u32,
+    pub executed: bool,
+    pub bump: u8,
+}
+
+impl MultisigTransaction {
+    pub const LEN: usize = 8 + 32 + 8 + 32
+        + (4 + MAX_TRANSACTION_ACCOUNTS * TransactionAccount::LEN)
+        + (4 + MAX_TRANSACTION_DATA_LEN)
+        + (4 + MAX_MULTISIG_MEMBERS)
+        + 4 + 1 + 1;
+
+    pub fn approval_count(&self) -> usize {
+        self.approvals.iter().filter(|approved| **approved).count()
+    }
+
+    // Unexecuted, and proposed under the multisig's current members
+    pub fn require_pending(&self, multisig: &Multisig) -> Result<()> {
+        if self.executed {
+            return Err(error!(MultisigError::AlreadyExecuted));
+        }
+        if self.member_set_seqno != multisig.member_set_seqno {
+            return Err(error!(MultisigError::StaleTransaction));
+        }
+        Ok(())
+    }
+
+    pub fn approve(&mut self, multisig: &Multisig, member: &Pubkey) -> Result<()> {
+        self.require_pending(multisig)?;
+        let member_index = multisig.member_index(member)
+            .ok_or(MultisigError::NotAMember)?;
+        self.approvals[member_index] = true;
+        Ok(())
+    }
+
+    // Pending and approved by at least the threshold of members
+    pub fn require_approved(&self, multisig: &Multisig) -> Result<()> {
+        self.require_pending(multisig)?;
+        if self.approval_count() < multisig.threshold as usize {
+            return Err(error!(MultisigError::NotEnoughApprovals));
+        }
+        Ok(())
+    }
+}
+
+// Whether `data` calls one of MULTISIG_INSTRUCTIONS, by Anchor sighash
+pub fn is_whitelisted(data: &[u8]) -> bool {
+    let Some(discriminator) = data.get(..8) else {
+        return false;
+    };
+    MULTISIG_INSTRUCTIONS.iter().any(|name| {
+        hash(format!("global:{name}").as_bytes()).to_bytes()[..8] == *discriminator
+    })
+}
+
+#[event]
+pub struct MultisigTransactionProposed {
+    pub multisig: Pubkey,
+    pub transaction: Pubkey,
+    pub proposer: Pubkey,
+}
+
+#[event]
+pub struct MultisigTransactionApproved {
+    pub transaction: Pubkey,
+    pub member: Pubkey,
+    pub approvals: u8,
+}
+
+#[event]
+pub struct MultisigTransactionExecuted {
+    pub transaction: Pubkey,
+    pub executed_by: Pubkey,
+}
+
+// Multisig errors
+#[error_code]
+pub enum MultisigError {
+    #[msg("Members must be unique and within the member limit")]
+    InvalidMembers,
+    #[msg("Threshold must be between one and the member count")]
+    InvalidThreshold,
+    #[msg("Signer is not a multisig member")]
+    NotAMember,
+    #[msg("Instruction is not executable by a multisig")]
+    InstructionNotWhitelisted,
+    #[msg("Transaction exceeds the account or data limit")]
+    TransactionTooLarge,
+    #[msg("Not enough approvals")]
+    NotEnoughApprovals,
+    #[msg("Transaction already executed")]
+    AlreadyExecuted,
+    #[msg("Multisig members changed since the proposal")]
+    StaleTransaction,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn multisig(members: Vec<Pubkey>, threshold: u8) -> Multisig {
+        Multisig {
+            create_key: Pubkey::default(),
+            members,
+            threshold,
+            nonce: 0,
+            member_set_seqno: 0,
+            bump: 0,
+        }
+    }
+
+    fn transaction(multisig: &Multisig) -> MultisigTransaction {
+        MultisigTransaction {
+            multisig: Pubkey::default(),
+            index: 0,
+            proposer: multisig.members[0],
+            accounts: Vec::new(),
+            data: Vec::new(),
+            approvals: vec![false; multisig.members.len()],
+            member_set_seqno: multisig.member_set_seqno,
+            executed: false,
+            bump: 0,
+        }
+    }
+
+    fn members(count: usize) -> Vec<Pubkey> {
+        (0..count).map(|_| Pubkey::new_unique()).collect()
+    }
+
+    #[test]
+    fn members_must_be_unique_and_bounded() {
+        let three = members(3);
+        assert!(validate_members(&three, 2).is_ok());
+        assert!(validate_members(&members(MAX_MULTISIG_MEMBERS), 1).is_ok());
+
+        let duplicated = vec![three[0], three[1], three[0]];
+        assert_eq!(validate_members(&duplicated, 1), Err(error!(MultisigError::InvalidMembers)));
+        assert_eq!(validate_members(&[], 1), Err(error!(MultisigError::InvalidMembers)));
+        assert_eq!(
+            validate_members(&members(MAX_MULTISIG_MEMBERS + 1), 1),
+            Err(error!(MultisigError::InvalidMembers))
+        );
+    }
+
+    #[test]
+    fn threshold_must_be_reachable() {
+        let three = members(3);
+        assert_eq!(validate_members(&three, 0), Err(error!(MultisigError::InvalidThreshold)));
+        assert_eq!(validate_members(&three, 4), Err(error!(MultisigError::InvalidThreshold)));
+        assert!(validate_members(&three, 3).is_ok());
+    }
+
+    #[test]
+    fn only_whitelisted_instructions_are_proposed() {
+        let sighash = |name: &str| hash(format!("global:{name}").as_bytes()).to_bytes()[..8].to_vec();
+
+        for name in MULTISIG_INSTRUCTIONS {
+            assert!(is_whitelisted(&sighash(name)));
+        }
+        // Trailing instruction arguments do not matter
+        let mut with_args = sighash("mint_token");
+        with_args.extend_from_slice(&42u64.to_le_bytes());
+        assert!(is_whitelisted(&with_args));
+
+        assert!(!is_whitelisted(&sighash("grant_role")));
+        assert!(!is_whitelisted(&sighash("mint_token")[..7]));
+        assert!(!is_whitelisted(&[]));
+    }
+
+    #[test]
+    fn approvals_count_up_to_the_threshold() {
+        let multisig = multisig(members(3), 2);
+        let mut proposal = transaction(&multisig);
+        proposal.approve(&multisig, &multisig.members[0]).unwrap();
+        assert_eq!(proposal.require_approved(&multisig), Err(error!(MultisigError::NotEnoughApprovals)));
+
+        // Approving twice counts once
+        proposal.approve(&multisig, &multisig.members[0]).unwrap();
+        assert_eq!(proposal.approval_count(), 1);
+
+        assert_eq!(
+            proposal.approve(&multisig, &Pubkey::new_unique()),
+            Err(error!(MultisigError::NotAMember))
+        );
+
+        proposal.approve(&multisig, &multisig.members[2]).unwrap();
+        assert_eq!(proposal.approval_count(), 2);
+        assert!(proposal.require_approved(&multisig).is_ok());
+
+        proposal.executed = true;
+        assert_eq!(proposal.require_approved(&multisig), Err(error!(MultisigError::AlreadyExecuted)));
+    }
+
+    #[test]
+    fn membership_changes_make_proposals_stale() {
+        let mut multisig = multisig(members(2), 1);
+        let mut proposal = transaction(&multisig);
+        proposal.approve(&multisig, &multisig.members[0]).unwrap();
+        assert!(proposal.require_approved(&multisig).is_ok());
+
+        multisig.member_set_seqno += 1;
+        assert_eq!(proposal.require_approved(&multisig), Err(error!(MultisigError::StaleTransaction)));
+        assert_eq!(
+            proposal.approve(&multisig, &multisig.members[1]),
+            Err(error!(MultisigError::StaleTransaction))
+        );
+    }
+}
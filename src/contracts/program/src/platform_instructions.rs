@@ -0,0 +1,223 @@
+use anchor_lang::prelude::*;
+use crate::access_control::{AccessControl, Role};
+use crate::platform_standards::*;
+use crate::timelock_standards::require_no_timelock;
+
+#[derive(Accounts)]
+pub struct InitializePlatformConfig<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = PlatformConfig::LEN,
+        seeds = [b"platform_config"],
+        bump
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(seeds = [b"access_control"], bump)]
+    pub access_control: Account<'info, AccessControl>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdatePlatformConfig<'info> {
+    #[account(mut, seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(seeds = [b"access_control"], bump)]
+    pub access_control: Account<'info, AccessControl>,
+    /// CHECK: Timelock queue PDA, only checked for existence
+    #[account(seeds = [b"timelock_queue"], bump)]
+    pub timelock_queue: UncheckedAccount<'info>,
+    pub authority: Signer<'info>,
+}
+
+// Platform config of a project instruction, rejected while projects are paused
+#[derive(Accounts)]
+pub struct ProjectsLive<'info> {
+    #[account(
+        seeds = [b"platform_config"],
+        bump = platform_config.bump,
+        constraint = !platform_config.is_paused(PAUSE_PROJECTS) @ PlatformError::Paused
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+}
+
+// Platform config of an exchange instruction, rejected while the exchange is paused
+#[derive(Accounts)]
+pub struct ExchangeLive<'info> {
+    #[account(
+        seeds = [b"platform_config"],
+        bump = platform_config.bump,
+        constraint = !platform_config.is_paused(PAUSE_EXCHANGE) @ PlatformError::Paused
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+}
+
+// Platform config of a token instruction, rejected while tokens are paused
+#[derive(Accounts)]
+pub struct TokensLive<'info> {
+    #[account(
+        seeds = [b"platform_config"],
+        bump = platform_config.bump,
+        constraint = !platform_config.is_paused(PAUSE_TOKENS) @ PlatformError::Paused
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+}
+
+// Platform config of a governance instruction, rejected while governance is paused
+#[derive(Accounts)]
+pub struct GovernanceLive<'info> {
+    #[account(
+        seeds = [b"platform_config"],
+        bump = platform_config.bump,
+        constraint = !platform_config.is_paused(PAUSE_GOVERNANCE) @ PlatformError::Paused
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+}
+
+// Platform instruction handlers
+pub fn handle_initialize_platform_config(
+    ctx: Context<InitializePlatformConfig>,
+    fee_bps: u16,
+    fee_exempt_roles: Vec<Role>,
+) -> Result<()> {
+    let authority = ctx.accounts.authority.key();
+    require_config_authority(&ctx.accounts.access_control, &authority)?;
+    validate_fee_config(fee_bps, &fee_exempt_roles)?;
+
+    let platform_config = &mut ctx.accounts.platform_config;
+    platform_config.fee_bps = fee_bps;
+    platform_config.treasuries = Vec::new();
+    platform_config.fee_exempt_roles = fee_exempt_roles;
+    platform_config.paused = false;
+    platform_config.paused_subsystems = 0;
+    platform_config.pending_unpause = None;
+    platform_config.bump = *ctx.bumps.get("platform_config").unwrap();
+
+    emit!(PlatformConfigUpdated {
+        fee_bps,
+        updated_by: authority,
+    });
+
+    Ok(())
+}
+
+pub fn handle_update_fee_config(
+    ctx: Context<UpdatePlatformConfig>,
+    fee_bps: u16,
+    fee_exempt_roles: Vec<Role>,
+) -> Result<()> {
+    let authority = ctx.accounts.authority.key();
+    require_config_authority(&ctx.accounts.access_control, &authority)?;
+    require_no_timelock(&ctx.accounts.timelock_queue)?;
+
+    ctx.accounts.platform_config.set_fee_config(fee_bps, fee_exempt_roles)?;
+
+    emit!(PlatformConfigUpdated {
+        fee_bps,
+        updated_by: authority,
+    });
+
+    Ok(())
+}
+
+// Point the fees of one payment mint at a treasury token account
+pub fn handle_set_treasury(
+    ctx: Context<UpdatePlatformConfig>,
+    mint: Pubkey,
+    treasury: Option<Pubkey>,
+) -> Result<()> {
+    let authority = ctx.accounts.authority.key();
+    require_config_authority(&ctx.accounts.access_control, &authority)?;
+    require_no_timelock(&ctx.accounts.timelock_queue)?;
+
+    ctx.accounts.platform_config.set_treasury(mint, treasury)?;
+
+    emit!(TreasuryUpdated {
+        mint,
+        treasury,
+        updated_by: authority,
+    });
+
+    Ok(())
+}
+
+// Admins and guardians stop the whole platform or individual subsystems.
+// Pausing again discards any queued unpause.
+pub fn handle_pause(
+    ctx: Context<UpdatePlatformConfig>,
+    global: bool,
+    subsystems: u8,
+) -> Result<()> {
+    let authority = ctx.accounts.authority.key();
+    require_pause_authority(&ctx.accounts.access_control, &authority)?;
+    if subsystems & !PAUSE_ALL != 0 {
+        return Err(error!(PlatformError::InvalidSubsystems));
+    }
+
+    let platform_config = &mut ctx.accounts.platform_config;
+    platform_config.paused |= global;
+    platform_config.paused_subsystems |= subsystems;
+    platform_config.pending_unpause = None;
+
+    emit_pause_updated(platform_config, authority);
+    Ok(())
+}
+
+// Admins and guardians queue an unpause that takes effect after UNPAUSE_DELAY
+pub fn handle_queue_unpause(
+    ctx: Context<UpdatePlatformConfig>,
+    global: bool,
+    subsystems: u8,
+) -> Result<()> {
+    let authority = ctx.accounts.authority.key();
+    require_pause_authority(&ctx.accounts.access_control, &authority)?;
+    if subsystems & !PAUSE_ALL != 0 {
+        return Err(error!(PlatformError::InvalidSubsystems));
+    }
+
+    let platform_config = &mut ctx.accounts.platform_config;
+    platform_config.pending_unpause = Some(PendingUnpause {
+        global,
+        subsystems,
+        eta: Clock::get()?.unix_timestamp + UNPAUSE_DELAY,
+    });
+
+    emit_pause_updated(platform_config, authority);
+    Ok(())
+}
+
+// Governance lifts a pause at once; anyone else may only apply a queued
+// unpause whose delay has passed
+pub fn handle_unpause(
+    ctx: Context<UpdatePlatformConfig>,
+    global: bool,
+    subsystems: u8,
+) -> Result<()> {
+    let authority = ctx.accounts.authority.key();
+    let access_control = &ctx.accounts.access_control;
+    let platform_config = &mut ctx.accounts.platform_config;
+
+    platform_config.require_unpause_allowed(
+        access_control,
+        &authority,
+        global,
+        subsystems,
+        Clock::get()?.unix_timestamp,
+    )?;
+
+    platform_config.lift_pause(global, subsystems);
+
+    emit_pause_updated(platform_config, authority);
+    Ok(())
+}
+
+fn emit_pause_updated(platform_config: &PlatformConfig, updated_by: Pubkey) {
+    emit!(PauseUpdated {
+        paused: platform_config.paused,
+        paused_subsystems: platform_config.paused_subsystems,
+        pending_unpause: platform_config.pending_unpause.clone(),
+        updated_by,
+    });
+}
@@ -0,0 +1,367 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token;
+use crate::access_control::{AccessControl, Role};
+use crate::exchange_standards::BPS_DENOMINATOR;
+
+// Highest fee the platform may charge, 10%
+pub const MAX_FEE_BPS: u16 = 1_000;
+pub const MAX_FEE_EXEMPT_ROLES: usize = 8;
+pub const MAX_TREASURIES: usize = 8;
+
+// Subsystem pause bits of PlatformConfig.paused_subsystems. Governance
+// covers multisig transactions; staking is reserved.
+pub const PAUSE_PROJECTS: u8 = 1 << 0;
+pub const PAUSE_EXCHANGE: u8 = 1 << 1;
+pub const PAUSE_TOKENS: u8 = 1 << 2;
+pub const PAUSE_STAKING: u8 = 1 << 3;
+pub const PAUSE_GOVERNANCE: u8 = 1 << 4;
+pub const PAUSE_ALL: u8 =
+    PAUSE_PROJECTS | PAUSE_EXCHANGE | PAUSE_TOKENS | PAUSE_STAKING | PAUSE_GOVERNANCE;
+
+// Delay before an admin or guardian may lift a pause without governance, 48 hours
+pub const UNPAUSE_DELAY: i64 = 48 * 60 * 60;
+
+// Global platform settings, a singleton PDA at ["platform_config"]
+#[account]
+pub struct PlatformConfig {
+    pub fee_bps: u16,
+    // Token account that receives fees, one per payment mint
+    pub treasuries: Vec<MintTreasury>,
+    // Providers holding any of these roles pay no fee
+    pub fee_exempt_roles: Vec<Role>,
+    // Global circuit breaker, stops every subsystem
+    pub paused: bool,
+    pub paused_subsystems: u8,
+    // Unpause queued by an admin or guardian, waiting out UNPAUSE_DELAY
+    pub pending_unpause: Option<PendingUnpause>,
+    pub bump: u8,
+}
+
+impl PlatformConfig {
+    pub const LEN: usize = 8 + 2 + (4 + MAX_TREASURIES * MintTreasury::LEN)
+        + (4 + MAX_FEE_EXEMPT_ROLES) + 1 + 1 + (1 + PendingUnpause::LEN) + 1;
+
+    pub fn set_fee_config(&mut self, fee_bps: u16, fee_exempt_roles: Vec<Role>) -> Result<()> {
+        validate_fee_config(fee_bps, &fee_exempt_roles)?;
+        self.fee_bps = fee_bps;
+        self.fee_exempt_roles = fee_exempt_roles;
+        Ok(())
+    }
+
+    // Register, replace or with None remove the fee account for `mint`
+    pub fn set_treasury(&mut self, mint: Pubkey, account: Option<Pubkey>) -> Result<()> {
+        self.treasuries.retain(|treasury| treasury.mint != mint);
+        if let Some(account) = account {
+            if self.treasuries.len() >= MAX_TREASURIES {
+                return Err(error!(PlatformError::TooManyTreasuries));
+            }
+            self.treasuries.push(MintTreasury { mint, account });
+        }
+        Ok(())
+    }
+
+    pub fn treasury_for(&self, mint: &Pubkey) -> Option<Pubkey> {
+        self.treasuries.iter()
+            .find(|treasury| treasury.mint == *mint)
+            .map(|treasury| treasury.account)
+    }
+
+    // Whether `account` is the registered fee account for `mint`
+    pub fn is_treasury(&self, mint: &Pubkey, account: &Pubkey) -> bool {
+        self.treasury_for(mint) == Some(*account)
+    }
+
+    // Lift the given pauses and drop any queued unpause
+    pub fn lift_pause(&mut self, global: bool, subsystems: u8) {
+        if global {
+            self.paused = false;
+        }
+        self.paused_subsystems &= !subsystems;
+        self.pending_unpause = None;
+    }
+
+    pub fn is_paused(&self, subsystem: u8) -> bool {
+        self.paused || self.paused_subsystems & subsystem != 0
+    }
+
+    // An explicit Governance role lifts a pause at once; the admin shortcut of
+    // has_role does not apply. Admins and guardians may only apply a matching
+    // queued unpause once its delay has passed.
+    pub fn require_unpause_allowed(
+        &self,
+        access_control: &AccessControl,
+        user: &Pubkey,
+        global: bool,
+        subsystems: u8,
+        now: i64,
+    ) -> Result<()> {
+        if access_control.roles.iter().any(|(u, r)| u == user && *r == Role::Governance) {
+            return Ok(());
+        }
+        require_pause_authority(access_control, user)?;
+        let ready = self.pending_unpause.as_ref().is_some_and(|pending| {
+            pending.global == global && pending.subsystems == subsystems && now >= pending.eta
+        });
+        if !ready {
+            return Err(error!(PlatformError::UnpauseNotReady));
+        }
+        Ok(())
+    }
+
+    pub fn is_fee_exempt(&self, access_control: &AccessControl, user: &Pubkey) -> bool {
+        self.fee_exempt_roles.iter().any(|role| {
+            access_control.roles.iter().any(|(u, r)| u == user && r == role)
+        })
+    }
+
+    // Split `amount` into (fee, net). The fee is rounded down, so amounts too
+    // small to owe a whole base unit pay nothing and the payee never receives
+    // less than `amount - amount * fee_bps / 10_000`.
+    pub fn split_fee(&self, amount: u64, exempt: bool) -> Result<(u64, u64)> {
+        if exempt || self.fee_bps == 0 {
+            return Ok((0, amount));
+        }
+        let fee = (amount as u128)
+            .checked_mul(self.fee_bps as u128)
+            .map(|product| product / BPS_DENOMINATOR as u128)
+            .ok_or(PlatformError::FeeOverflow)? as u64;
+        Ok((fee, amount - fee))
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq)]
+pub struct MintTreasury {
+    pub mint: Pubkey,
+    pub account: Pubkey,
+}
+
+impl MintTreasury {
+    pub const LEN: usize = 32 + 32;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq)]
+pub struct PendingUnpause {
+    pub global: bool,
+    pub subsystems: u8,
+    pub eta: i64,
+}
+
+impl PendingUnpause {
+    pub const LEN: usize = 1 + 1 + 8;
+}
+
+#[event]
+pub struct FeeCollected {
+    pub mint: Pubkey,
+    pub payee: Pubkey,
+    pub gross: u64,
+    pub fee: u64,
+}
+
+#[event]
+pub struct PauseUpdated {
+    pub paused: bool,
+    pub paused_subsystems: u8,
+    pub pending_unpause: Option<PendingUnpause>,
+    pub updated_by: Pubkey,
+}
+
+#[event]
+pub struct PlatformConfigUpdated {
+    pub fee_bps: u16,
+    pub updated_by: Pubkey,
+}
+
+#[event]
+pub struct TreasuryUpdated {
+    pub mint: Pubkey,
+    pub treasury: Option<Pubkey>,
+    pub updated_by: Pubkey,
+}
+
+// Platform errors
+#[error_code]
+pub enum PlatformError {
+    #[msg("Fee exceeds the platform maximum")]
+    FeeTooHigh,
+    #[msg("Too many fee exempt roles")]
+    TooManyExemptRoles,
+    #[msg("Fee calculation overflow")]
+    FeeOverflow,
+    #[msg("Unauthorized: Admin or governance access required")]
+    UnauthorizedConfigChange,
+    #[msg("This subsystem is paused")]
+    Paused,
+    #[msg("Unauthorized: Admin or guardian access required")]
+    UnauthorizedPause,
+    #[msg("No matching unpause is queued, or its delay has not passed")]
+    UnpauseNotReady,
+    #[msg("Unknown subsystem pause bits")]
+    InvalidSubsystems,
+    #[msg("Too many treasury accounts")]
+    TooManyTreasuries,
+    #[msg("Account is not the platform treasury for this mint")]
+    UnknownTreasury,
+}
+
+// Pay `amount` out of `from`, sending the platform fee to the treasury and
+// the rest to the payee. Returns the fee taken.
+#[allow(clippy::too_many_arguments)]
+pub fn pay_with_fee<'info>(
+    platform_config: &PlatformConfig,
+    exempt: bool,
+    token_program: &AccountInfo<'info>,
+    from: &AccountInfo<'info>,
+    payee_account: &AccountInfo<'info>,
+    treasury_account: &AccountInfo<'info>,
+    authority: &AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+    amount: u64,
+) -> Result<u64> {
+    let (fee, net) = platform_config.split_fee(amount, exempt)?;
+
+    for (to, value) in [(payee_account, net), (treasury_account, fee)] {
+        if value == 0 {
+            continue;
+        }
+        token::transfer(
+            CpiContext::new_with_signer(
+                token_program.clone(),
+                token::Transfer {
+                    from: from.clone(),
+                    to: to.clone(),
+                    authority: authority.clone(),
+                },
+                signer_seeds,
+            ),
+            value,
+        )?;
+    }
+
+    Ok(fee)
+}
+
+// Config changes are reserved for admins and governance
+pub fn require_config_authority(access_control: &AccessControl, user: &Pubkey) -> Result<()> {
+    if !access_control.is_admin(user) && !access_control.has_role(user, &Role::Governance) {
+        return Err(error!(PlatformError::UnauthorizedConfigChange));
+    }
+    Ok(())
+}
+
+// Pausing is open to admins and guardians so an incident can be contained
+// without waiting on governance
+pub fn require_pause_authority(access_control: &AccessControl, user: &Pubkey) -> Result<()> {
+    if !access_control.is_admin(user) && !access_control.has_role(user, &Role::Guardian) {
+        return Err(error!(PlatformError::UnauthorizedPause));
+    }
+    Ok(())
+}
+
+pub fn validate_fee_config(fee_bps: u16, fee_exempt_roles: &[Role]) -> Result<()> {
+    if fee_bps > MAX_FEE_BPS {
+        return Err(error!(PlatformError::FeeTooHigh));
+    }
+    if fee_exempt_roles.len() > MAX_FEE_EXEMPT_ROLES {
+        return Err(error!(PlatformError::TooManyExemptRoles));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(fee_bps: u16) -> PlatformConfig {
+        PlatformConfig {
+            fee_bps,
+            treasuries: Vec::new(),
+            fee_exempt_roles: Vec::new(),
+            paused: false,
+            paused_subsystems: 0,
+            pending_unpause: None,
+            bump: 0,
+        }
+    }
+
+    #[test]
+    fn fee_rounds_down_in_favor_of_the_payee() {
+        let config = config(250);
+        assert_eq!(config.split_fee(10_000, false).unwrap(), (250, 9_750));
+        assert_eq!(config.split_fee(399, false).unwrap(), (9, 390));
+        // 39 * 2.5% = 0.975 base units, too little to owe anything
+        assert_eq!(config.split_fee(39, false).unwrap(), (0, 39));
+        assert_eq!(config.split_fee(0, false).unwrap(), (0, 0));
+    }
+
+    #[test]
+    fn fee_handles_the_full_u64_range() {
+        let (fee, net) = config(MAX_FEE_BPS).split_fee(u64::MAX, false).unwrap();
+        assert_eq!(fee, u64::MAX / 10);
+        assert_eq!(fee + net, u64::MAX);
+    }
+
+    #[test]
+    fn exempt_and_zero_bps_pay_no_fee() {
+        assert_eq!(config(250).split_fee(10_000, true).unwrap(), (0, 10_000));
+        assert_eq!(config(0).split_fee(10_000, false).unwrap(), (0, 10_000));
+    }
+
+    #[test]
+    fn treasuries_are_kept_per_mint() {
+        let mut config = config(250);
+        let (usdc, project_token) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (first, second) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+        config.set_treasury(usdc, Some(first)).unwrap();
+        assert!(config.is_treasury(&usdc, &first));
+        assert!(!config.is_treasury(&project_token, &first));
+
+        config.set_treasury(usdc, Some(second)).unwrap();
+        assert_eq!(config.treasuries.len(), 1);
+        assert_eq!(config.treasury_for(&usdc), Some(second));
+
+        config.set_treasury(usdc, None).unwrap();
+        assert_eq!(config.treasury_for(&usdc), None);
+    }
+
+    #[test]
+    fn treasury_count_is_bounded() {
+        let mut config = config(250);
+        for _ in 0..MAX_TREASURIES {
+            config.set_treasury(Pubkey::new_unique(), Some(Pubkey::new_unique())).unwrap();
+        }
+        assert!(config.set_treasury(Pubkey::new_unique(), Some(Pubkey::new_unique())).is_err());
+    }
+
+    #[test]
+    fn only_an_explicit_governance_role_unpauses_at_once() {
+        let (admin, governance, guardian) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let access_control = AccessControl {
+            admin,
+            roles: vec![(governance, Role::Governance), (guardian, Role::Guardian)],
+        };
+        let mut config = PlatformConfig { paused: true, ..config(0) };
+        let allowed = |config: &PlatformConfig, user: &Pubkey, now: i64| {
+            config.require_unpause_allowed(&access_control, user, true, 0, now)
+        };
+
+        assert!(allowed(&config, &governance, 0).is_ok());
+        assert_eq!(allowed(&config, &admin, 0), Err(error!(PlatformError::UnpauseNotReady)));
+        assert_eq!(allowed(&config, &guardian, 0), Err(error!(PlatformError::UnpauseNotReady)));
+        assert_eq!(
+            allowed(&config, &Pubkey::new_unique(), 0),
+            Err(error!(PlatformError::UnauthorizedPause))
+        );
+
+        // A queued unpause opens up to admins and guardians once its eta passes
+        config.pending_unpause = Some(PendingUnpause { global: true, subsystems: 0, eta: 100 });
+        assert_eq!(allowed(&config, &admin, 99), Err(error!(PlatformError::UnpauseNotReady)));
+        assert!(allowed(&config, &admin, 100).is_ok());
+        assert!(allowed(&config, &guardian, 100).is_ok());
+        assert_eq!(
+            config.require_unpause_allowed(&access_control, &admin, false, PAUSE_TOKENS, 100),
+            Err(error!(PlatformError::UnpauseNotReady))
+        );
+    }
+}
@@ -0,0 +1,83 @@
+use anchor_lang::prelude::*;
+use crate::platform_instructions::*;
+use crate::*;
+
+#[derive(Accounts)]
+pub struct ModifyResource<'info> {
+    #[account(mut, has_one = provider)]
+    pub resource: Account<'info, Resource>,
+    pub platform: ExchangeLive<'info>,
+    pub provider: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseResource<'info> {
+    #[account(mut, close = provider, has_one = provider)]
+    pub resource: Account<'info, Resource>,
+    pub platform: ExchangeLive<'info>,
+    #[account(mut)]
+    pub provider: Signer<'info>,
+}
+
+// Resource instruction handlers
+pub fn handle_update_resource(
+    ctx: Context<ModifyResource>,
+    description: Option<String>,
+    resource_type: Option<ResourceType>,
+    price: Option<u64>,
+) -> Result<()> {
+    let resource = &mut ctx.accounts.resource;
+
+    // Listings can only be edited while on or off the market
+    match resource.status {
+        ResourceStatus::Available | ResourceStatus::Delisted => {}
+        ResourceStatus::Reserved => return Err(error!(ResourceError::ResourceReserved)),
+        _ => return Err(error!(ResourceError::ResourceUnavailable)),
+    }
+
+    if let Some(description) = description {
+        if description.len() > MAX_RESOURCE_DESCRIPTION_LEN {
+            return Err(error!(ResourceError::FieldTooLong));
+        }
+        resource.description = description;
+    }
+    if let Some(resource_type) = resource_type {
+        resource.resource_type = resource_type;
+    }
+    if let Some(price) = price {
+        resource.price = price;
+    }
+
+    Ok(())
+}
+
+pub fn handle_delist_resource(ctx: Context<ModifyResource>) -> Result<()> {
+    let resource = &mut ctx.accounts.resource;
+    resource.status.ensure_transition(&ResourceStatus::Delisted)?;
+    resource.status = ResourceStatus::Delisted;
+    Ok(())
+}
+
+pub fn handle_relist_resource(ctx: Context<ModifyResource>) -> Result<()> {
+    let resource = &mut ctx.accounts.resource;
+    // Reserved resources return to the market through their exchange, not here
+    if resource.status == ResourceStatus::Reserved {
+        return Err(error!(ResourceError::ResourceReserved));
+    }
+    if resource.status == ResourceStatus::Available {
+        return Err(error!(ResourceError::InvalidStatusTransition));
+    }
+    resource.status.ensure_transition(&ResourceStatus::Available)?;
+    resource.status = ResourceStatus::Available;
+    Ok(())
+}
+
+pub fn handle_close_resource(ctx: Context<CloseResource>) -> Result<()> {
+    let resource = &mut ctx.accounts.resource;
+    if resource.open_commitments > 0 {
+        return Err(error!(ResourceError::OpenCommitments));
+    }
+    resource.status.ensure_transition(&ResourceStatus::Closed)?;
+    resource.status = ResourceStatus::Closed;
+    Ok(())
+}
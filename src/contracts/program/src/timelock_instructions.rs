@@ -0,0 +1,247 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Mint, Token};
+use crate::access_control::{require_admin, AccessControl};
+use crate::platform_standards::{PlatformConfig, PlatformError, PAUSE_GOVERNANCE};
+use crate::timelock_standards::*;
+use crate::token_instructions::drop_mint_authority;
+use crate::token_standards::{
+    TokenAuthorityRenounced, TokenAuthorityTransferStarted, TokenError, TokenInfo,
+};
+
+#[derive(Accounts)]
+pub struct InitializeTimelock<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = TimelockQueue::LEN,
+        seeds = [b"timelock_queue"],
+        bump
+    )]
+    pub timelock_queue: Account<'info, TimelockQueue>,
+    #[account(seeds = [b"access_control"], bump)]
+    pub access_control: Account<'info, AccessControl>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct QueueAction<'info> {
+    #[account(mut, seeds = [b"timelock_queue"], bump = timelock_queue.bump)]
+    pub timelock_queue: Account<'info, TimelockQueue>,
+    #[account(
+        init,
+        payer = proposer,
+        space = QueuedAction::LEN,
+        seeds = [b"timelock_action".as_ref(), &timelock_queue.action_count.to_le_bytes()],
+        bump
+    )]
+    pub queued_action: Account<'info, QueuedAction>,
+    #[account(seeds = [b"access_control"], bump)]
+    pub access_control: Account<'info, AccessControl>,
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    // Required for token actions, which the token authority proposes
+    pub token_info: Option<Account<'info, TokenInfo>>,
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelAction<'info> {
+    #[account(
+        mut,
+        seeds = [b"timelock_action".as_ref(), &queued_action.index.to_le_bytes()],
+        bump = queued_action.bump
+    )]
+    pub queued_action: Account<'info, QueuedAction>,
+    #[account(seeds = [b"access_control"], bump)]
+    pub access_control: Account<'info, AccessControl>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteAction<'info> {
+    #[account(mut, seeds = [b"timelock_queue"], bump = timelock_queue.bump)]
+    pub timelock_queue: Account<'info, TimelockQueue>,
+    #[account(
+        mut,
+        seeds = [b"timelock_action".as_ref(), &queued_action.index.to_le_bytes()],
+        bump = queued_action.bump
+    )]
+    pub queued_action: Account<'info, QueuedAction>,
+    #[account(mut, seeds = [b"access_control"], bump)]
+    pub access_control: Account<'info, AccessControl>,
+    #[account(mut, seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+    // Required for token actions
+    #[account(mut)]
+    pub token_info: Option<Account<'info, TokenInfo>>,
+    // Required to renounce a token authority, along with the token program
+    #[account(mut)]
+    pub mint: Option<Account<'info, Mint>>,
+    pub token_program: Option<Program<'info, Token>>,
+    pub executor: Signer<'info>,
+}
+
+// Timelock instruction handlers
+pub fn handle_initialize_timelock(ctx: Context<InitializeTimelock>, delay: i64) -> Result<()> {
+    require_admin(&ctx.accounts.access_control, &ctx.accounts.admin.key())?;
+    validate_delay(delay)?;
+
+    let timelock_queue = &mut ctx.accounts.timelock_queue;
+    timelock_queue.delay = delay;
+    timelock_queue.action_count = 0;
+    timelock_queue.bump = *ctx.bumps.get("timelock_queue").unwrap();
+
+    Ok(())
+}
+
+// Admins queue role changes, admins and governance the other platform
+// actions; a token's authority queues changes to that token
+pub fn handle_queue_action(ctx: Context<QueueAction>, action: TimelockAction) -> Result<()> {
+    let proposer = ctx.accounts.proposer.key();
+    require_live_or_unpause(&ctx.accounts.platform_config, &action)?;
+    require_proposer(
+        &action,
+        &ctx.accounts.access_control,
+        ctx.accounts.token_info.as_ref().map(|token_info| (token_info.key(), token_info.authority)),
+        &proposer,
+    )?;
+    validate_action(&action)?;
+
+    let timelock_queue = &mut ctx.accounts.timelock_queue;
+    let queued_action = &mut ctx.accounts.queued_action;
+    queued_action.index = timelock_queue.action_count;
+    queued_action.action = action;
+    queued_action.proposer = proposer;
+    queued_action.eta = Clock::get()?.unix_timestamp + timelock_queue.delay;
+    queued_action.status = TimelockStatus::Queued;
+    queued_action.bump = *ctx.bumps.get("queued_action").unwrap();
+
+    timelock_queue.action_count = timelock_queue.action_count.checked_add(1)
+        .ok_or(TimelockError::ActionCountOverflow)?;
+
+    emit!(TimelockQueued {
+        action: queued_action.key(),
+        index: queued_action.index,
+        proposer,
+        eta: queued_action.eta,
+    });
+
+    Ok(())
+}
+
+// Guardians, admins or the proposer veto a queued action
+pub fn handle_cancel_action(ctx: Context<CancelAction>) -> Result<()> {
+    let authority = ctx.accounts.authority.key();
+    let queued_action = &mut ctx.accounts.queued_action;
+    queued_action.cancel(&ctx.accounts.access_control, &authority)?;
+
+    emit!(TimelockCancelled {
+        action: queued_action.key(),
+        cancelled_by: authority,
+    });
+
+    Ok(())
+}
+
+// Anyone applies a queued action once its ETA has passed, within the grace period
+pub fn handle_execute_action(ctx: Context<ExecuteAction>) -> Result<()> {
+    let queued_action = &ctx.accounts.queued_action;
+    queued_action.require_executable(Clock::get()?.unix_timestamp)?;
+    require_live_or_unpause(&ctx.accounts.platform_config, &queued_action.action)?;
+    // The token may have changed hands or been renounced since queueing
+    if let Some(token_info) = queued_action.action.token_info() {
+        let proposer = queued_action.proposer;
+        if queued_token_info(&mut ctx.accounts.token_info, token_info)?.authority != proposer {
+            return Err(error!(TokenError::UnauthorizedOperation));
+        }
+    }
+
+    match queued_action.action.clone() {
+        TimelockAction::GrantRole { user, role } => {
+            ctx.accounts.access_control.grant(user, role)?;
+        }
+        TimelockAction::RevokeRole { user, role } => {
+            ctx.accounts.access_control.remove_role(&user, &role);
+        }
+        TimelockAction::UpdateFeeConfig { fee_bps, fee_exempt_roles } => {
+            ctx.accounts.platform_config.set_fee_config(fee_bps, fee_exempt_roles)?;
+        }
+        TimelockAction::Unpause { global, subsystems } => {
+            ctx.accounts.platform_config.lift_pause(global, subsystems);
+        }
+        TimelockAction::UpdateTokenPermissions { token_info, permissions } => {
+            queued_token_info(&mut ctx.accounts.token_info, token_info)?.permissions = permissions;
+        }
+        TimelockAction::UpdateDistributionRules { token_info, rules } => {
+            queued_token_info(&mut ctx.accounts.token_info, token_info)?.distribution_rules = rules;
+        }
+        TimelockAction::UpdateDelay { delay } => {
+            ctx.accounts.timelock_queue.delay = delay;
+        }
+        TimelockAction::SetTreasury { mint, treasury } => {
+            ctx.accounts.platform_config.set_treasury(mint, treasury)?;
+        }
+        TimelockAction::TransferTokenAuthority { token_info, new_authority } => {
+            let token_info = queued_token_info(&mut ctx.accounts.token_info, token_info)?;
+            match new_authority {
+                Some(new_authority) => token_info.start_authority_transfer(new_authority)?,
+                None => token_info.pending_authority = None,
+            }
+            emit!(TokenAuthorityTransferStarted {
+                token_info: token_info.key(),
+                authority: token_info.authority,
+                pending_authority: token_info.pending_authority,
+            });
+        }
+        TimelockAction::RenounceTokenAuthority { token_info } => {
+            let token_info = queued_token_info(&mut ctx.accounts.token_info, token_info)?;
+            let previous_authority = token_info.renounce_authority()?;
+            let mint = ctx.accounts.mint.as_ref()
+                .filter(|mint| mint.key() == token_info.mint)
+                .ok_or(TimelockError::ActionAccountMismatch)?;
+            let token_program = ctx.accounts.token_program.as_ref()
+                .ok_or(TimelockError::ActionAccountMismatch)?;
+            drop_mint_authority(token_info, mint, token_program)?;
+
+            emit!(TokenAuthorityRenounced {
+                token_info: token_info.key(),
+                previous_authority,
+            });
+        }
+    }
+
+    let queued_action = &mut ctx.accounts.queued_action;
+    queued_action.status = TimelockStatus::Executed;
+
+    emit!(TimelockExecuted {
+        action: queued_action.key(),
+        executed_by: ctx.accounts.executor.key(),
+    });
+
+    Ok(())
+}
+
+// Governance is frozen while paused, except for the timelocked unpause that
+// ends the pause
+fn require_live_or_unpause(platform_config: &PlatformConfig, action: &TimelockAction) -> Result<()> {
+    if platform_config.is_paused(PAUSE_GOVERNANCE)
+        && !matches!(action, TimelockAction::Unpause { .. })
+    {
+        return Err(error!(PlatformError::Paused));
+    }
+    Ok(())
+}
+
+fn queued_token_info<'a, 'info>(
+    token_info: &'a mut Option<Account<'info, TokenInfo>>,
+    expected: Pubkey,
+) -> Result<&'a mut Account<'info, TokenInfo>> {
+    match token_info {
+        Some(token_info) if token_info.key() == expected => Ok(token_info),
+        _ => Err(error!(TimelockError::ActionAccountMismatch)),
+    }
+}
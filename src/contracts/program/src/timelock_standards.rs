@@ -0,0 +1,395 @@
+use anchor_lang::prelude::*;
+use crate::access_control::{require_admin, AccessControl, AccessControlError, Role};
+use crate::platform_standards::{require_config_authority, validate_fee_config};
+use crate::token_standards::{DistributionRules, TokenError, TokenPermissions};
+
+// Bounds on the queue delay, 1 to 30 days
+pub const MIN_TIMELOCK_DELAY: i64 = 24 * 60 * 60;
+pub const MAX_TIMELOCK_DELAY: i64 = 30 * 24 * 60 * 60;
+// Window after the ETA in which a queued action may still execute, 14 days
+pub const TIMELOCK_GRACE_PERIOD: i64 = 14 * 24 * 60 * 60;
+
+// Queue of delayed admin actions, a singleton PDA at ["timelock_queue"].
+// Once it exists, role and fee changes can only be made through it.
+#[account]
+pub struct TimelockQueue {
+    pub delay: i64,
+    pub action_count: u64,
+    pub bump: u8,
+}
+
+impl TimelockQueue {
+    pub const LEN: usize = 8 + 8 + 8 + 1;
+}
+
+// Sensitive configuration change, applied only once its delay has passed
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub enum TimelockAction {
+    // AccessControl
+    GrantRole { user: Pubkey, role: Role },
+    RevokeRole { user: Pubkey, role: Role },
+    // PlatformConfig
+    UpdateFeeConfig { fee_bps: u16, fee_exempt_roles: Vec<Role> },
+    Unpause { global: bool, subsystems: u8 },
+    // TokenInfo
+    UpdateTokenPermissions { token_info: Pubkey, permissions: TokenPermissions },
+    UpdateDistributionRules { token_info: Pubkey, rules: DistributionRules },
+    // TimelockQueue
+    UpdateDelay { delay: i64 },
+    // PlatformConfig
+    SetTreasury { mint: Pubkey, treasury: Option<Pubkey> },
+    // TokenInfo: propose a new token authority, or cancel with None
+    TransferTokenAuthority { token_info: Pubkey, new_authority: Option<Pubkey> },
+    // TokenInfo: irreversibly drop the token authority and SPL mint authority
+    RenounceTokenAuthority { token_info: Pubkey },
+}
+
+impl TimelockAction {
+    // Largest variant is UpdateDistributionRules
+    pub const LEN: usize = 1 + 32 + DistributionRules::LEN;
+
+    // Token the action reconfigures, if any
+    pub fn token_info(&self) -> Option<Pubkey> {
+        match self {
+            TimelockAction::UpdateTokenPermissions { token_info, .. }
+            | TimelockAction::UpdateDistributionRules { token_info, .. }
+            | TimelockAction::TransferTokenAuthority { token_info, .. }
+            | TimelockAction::RenounceTokenAuthority { token_info } => Some(*token_info),
+            _ => None,
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq)]
+pub enum TimelockStatus {
+    Queued,
+    Cancelled,
+    Executed,
+}
+
+// Queued action, PDA at ["timelock_action", index]
+#[account]
+pub struct QueuedAction {
+    pub index: u64,
+    pub action: TimelockAction,
+    pub proposer: Pubkey,
+    pub eta: i64,
+    pub status: TimelockStatus,
+    pub bump: u8,
+}
+
+impl QueuedAction {
+    pub const LEN: usize = 8 + 8 + TimelockAction::LEN + 32 + 8 + 1 + 1;
+
+    // Guardians, admins or the proposer veto a pending action
+    pub fn cancel(&mut self, access_control: &AccessControl, authority: &Pubkey) -> Result<()> {
+        if *authority != self.proposer
+            && !access_control.is_admin(authority)
+            && !access_control.has_role(authority, &Role::Guardian)
+        {
+            return Err(error!(AccessControlError::UnauthorizedRole));
+        }
+        if self.status != TimelockStatus::Queued {
+            return Err(error!(TimelockError::ActionNotQueued));
+        }
+        self.status = TimelockStatus::Cancelled;
+        Ok(())
+    }
+
+    // A pending action executes from its ETA until the grace period runs out
+    pub fn require_executable(&self, now: i64) -> Result<()> {
+        if self.status != TimelockStatus::Queued {
+            return Err(error!(TimelockError::ActionNotQueued));
+        }
+        if now < self.eta {
+            return Err(error!(TimelockError::ActionNotReady));
+        }
+        if now > self.eta.saturating_add(TIMELOCK_GRACE_PERIOD) {
+            return Err(error!(TimelockError::ActionExpired));
+        }
+        Ok(())
+    }
+}
+
+#[event]
+pub struct TimelockQueued {
+    pub action: Pubkey,
+    pub index: u64,
+    pub proposer: Pubkey,
+    pub eta: i64,
+}
+
+#[event]
+pub struct TimelockCancelled {
+    pub action: Pubkey,
+    pub cancelled_by: Pubkey,
+}
+
+#[event]
+pub struct TimelockExecuted {
+    pub action: Pubkey,
+    pub executed_by: Pubkey,
+}
+
+// Timelock errors
+#[error_code]
+pub enum TimelockError {
+    #[msg("Timelock delay out of bounds")]
+    InvalidDelay,
+    #[msg("This change must go through the timelock queue")]
+    TimelockRequired,
+    #[msg("Queued action is not pending")]
+    ActionNotQueued,
+    #[msg("Queued action is not yet executable")]
+    ActionNotReady,
+    #[msg("Queued action has expired")]
+    ActionExpired,
+    #[msg("Account does not match the queued action")]
+    ActionAccountMismatch,
+    #[msg("Timelock action count overflow")]
+    ActionCountOverflow,
+}
+
+pub fn validate_delay(delay: i64) -> Result<()> {
+    if !(MIN_TIMELOCK_DELAY..=MAX_TIMELOCK_DELAY).contains(&delay) {
+        return Err(error!(TimelockError::InvalidDelay));
+    }
+    Ok(())
+}
+
+// Who may queue an action: a token's authority queues changes to that token,
+// only admins queue role changes, and admins or governance the rest.
+// `token_info` is the (address, authority) of the token account supplied.
+pub fn require_proposer(
+    action: &TimelockAction,
+    access_control: &AccessControl,
+    token_info: Option<(Pubkey, Pubkey)>,
+    proposer: &Pubkey,
+) -> Result<()> {
+    if let Some(expected) = action.token_info() {
+        require_action_account(expected, token_info.map(|(key, _)| key))?;
+        if token_info.map(|(_, authority)| authority) != Some(*proposer) {
+            return Err(error!(TokenError::UnauthorizedOperation));
+        }
+        return Ok(());
+    }
+    match action {
+        TimelockAction::GrantRole { .. } | TimelockAction::RevokeRole { .. } => {
+            require_admin(access_control, proposer)
+        }
+        _ => require_config_authority(access_control, proposer),
+    }
+}
+
+// The account supplied for an action must be the one it names
+pub fn require_action_account(expected: Pubkey, supplied: Option<Pubkey>) -> Result<()> {
+    if supplied != Some(expected) {
+        return Err(error!(TimelockError::ActionAccountMismatch));
+    }
+    Ok(())
+}
+
+// Reject actions that could never execute, before anyone waits on them
+pub fn validate_action(action: &TimelockAction) -> Result<()> {
+    match action {
+        TimelockAction::UpdateFeeConfig { fee_bps, fee_exempt_roles } => {
+            validate_fee_config(*fee_bps, fee_exempt_roles)
+        }
+        TimelockAction::UpdateDistributionRules { rules, .. } => rules.validate(),
+        TimelockAction::UpdateDelay { delay } => validate_delay(*delay),
+        _ => Ok(()),
+    }
+}
+
+// Direct admin changes are only allowed until the timelock queue is created
+pub fn require_no_timelock(timelock_queue: &AccountInfo) -> Result<()> {
+    if !timelock_queue.data_is_empty() {
+        return Err(error!(TimelockError::TimelockRequired));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_actions_name_their_token() {
+        let token_info = Pubkey::new_unique();
+        let transfer = TimelockAction::TransferTokenAuthority {
+            token_info,
+            new_authority: Some(Pubkey::new_unique()),
+        };
+        assert_eq!(transfer.token_info(), Some(token_info));
+        assert_eq!(TimelockAction::UpdateDelay { delay: MIN_TIMELOCK_DELAY }.token_info(), None);
+    }
+
+    #[test]
+    fn delay_bounds() {
+        assert!(validate_delay(MIN_TIMELOCK_DELAY).is_ok());
+        assert!(validate_delay(MAX_TIMELOCK_DELAY).is_ok());
+        assert!(validate_delay(MIN_TIMELOCK_DELAY - 1).is_err());
+        assert!(validate_delay(MAX_TIMELOCK_DELAY + 1).is_err());
+    }
+
+    fn queued(action: TimelockAction, proposer: Pubkey, eta: i64) -> QueuedAction {
+        QueuedAction {
+            index: 0,
+            action,
+            proposer,
+            eta,
+            status: TimelockStatus::Queued,
+            bump: 0,
+        }
+    }
+
+    fn rules(vesting_period: Option<i64>, cliff_period: Option<i64>) -> DistributionRules {
+        DistributionRules {
+            vesting_period,
+            cliff_period,
+            transfer_lock: None,
+            min_hold_period: None,
+        }
+    }
+
+    #[test]
+    fn actions_execute_between_eta_and_grace_end() {
+        let action = queued(TimelockAction::UpdateDelay { delay: MIN_TIMELOCK_DELAY }, Pubkey::new_unique(), 1_000);
+        assert_eq!(action.require_executable(999), Err(error!(TimelockError::ActionNotReady)));
+        assert!(action.require_executable(1_000).is_ok());
+        assert!(action.require_executable(1_000 + TIMELOCK_GRACE_PERIOD).is_ok());
+        assert_eq!(
+            action.require_executable(1_001 + TIMELOCK_GRACE_PERIOD),
+            Err(error!(TimelockError::ActionExpired))
+        );
+    }
+
+    #[test]
+    fn executed_and_cancelled_actions_cannot_run_again() {
+        let action = queued(TimelockAction::UpdateDelay { delay: MIN_TIMELOCK_DELAY }, Pubkey::new_unique(), 0);
+        let executed = QueuedAction { status: TimelockStatus::Executed, ..action.clone() };
+        assert_eq!(executed.require_executable(0), Err(error!(TimelockError::ActionNotQueued)));
+        let cancelled = QueuedAction { status: TimelockStatus::Cancelled, ..action };
+        assert_eq!(cancelled.require_executable(0), Err(error!(TimelockError::ActionNotQueued)));
+    }
+
+    #[test]
+    fn cancel_is_limited_to_proposer_admins_and_guardians() {
+        let (admin, guardian, proposer) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut access_control = AccessControl::new(admin);
+        access_control.grant(guardian, Role::Guardian).unwrap();
+        let action = queued(TimelockAction::Unpause { global: true, subsystems: 0 }, proposer, 0);
+
+        let mut pending = action.clone();
+        assert_eq!(
+            pending.cancel(&access_control, &Pubkey::new_unique()),
+            Err(error!(AccessControlError::UnauthorizedRole))
+        );
+        assert!(pending.status == TimelockStatus::Queued);
+
+        for canceller in [admin, guardian, proposer] {
+            let mut pending = action.clone();
+            pending.cancel(&access_control, &canceller).unwrap();
+            assert!(pending.status == TimelockStatus::Cancelled);
+            assert_eq!(pending.cancel(&access_control, &canceller), Err(error!(TimelockError::ActionNotQueued)));
+            assert_eq!(pending.require_executable(0), Err(error!(TimelockError::ActionNotQueued)));
+        }
+    }
+
+    #[test]
+    fn role_changes_are_queued_by_admins_only() {
+        let (admin, governance) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut access_control = AccessControl::new(admin);
+        access_control.grant(governance, Role::Governance).unwrap();
+        let grant = TimelockAction::GrantRole { user: governance, role: Role::Admin };
+        let revoke = TimelockAction::RevokeRole { user: admin, role: Role::Admin };
+
+        for action in [&grant, &revoke] {
+            assert!(require_proposer(action, &access_control, None, &admin).is_ok());
+            assert_eq!(
+                require_proposer(action, &access_control, None, &governance),
+                Err(error!(AccessControlError::UnauthorizedAdmin))
+            );
+        }
+
+        // Other platform actions stay open to governance
+        let delay = TimelockAction::UpdateDelay { delay: MIN_TIMELOCK_DELAY };
+        assert!(require_proposer(&delay, &access_control, None, &governance).is_ok());
+        assert!(require_proposer(&delay, &access_control, None, &Pubkey::new_unique()).is_err());
+    }
+
+    #[test]
+    fn token_actions_need_their_token_and_its_authority() {
+        let access_control = AccessControl::new(Pubkey::new_unique());
+        let (token_info, authority) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let action = TimelockAction::RenounceTokenAuthority { token_info };
+
+        assert!(require_proposer(&action, &access_control, Some((token_info, authority)), &authority).is_ok());
+        assert_eq!(
+            require_proposer(&action, &access_control, None, &authority),
+            Err(error!(TimelockError::ActionAccountMismatch))
+        );
+        assert_eq!(
+            require_proposer(&action, &access_control, Some((Pubkey::new_unique(), authority)), &authority),
+            Err(error!(TimelockError::ActionAccountMismatch))
+        );
+        assert_eq!(
+            require_proposer(&action, &access_control, Some((token_info, authority)), &access_control.admin),
+            Err(error!(TokenError::UnauthorizedOperation))
+        );
+
+        assert!(require_action_account(token_info, Some(token_info)).is_ok());
+        assert!(require_action_account(token_info, Some(authority)).is_err());
+        assert!(require_action_account(token_info, None).is_err());
+    }
+
+    #[test]
+    fn every_action_is_validated_up_front() {
+        let token_info = Pubkey::new_unique();
+        let valid = [
+            TimelockAction::GrantRole { user: Pubkey::new_unique(), role: Role::Guardian },
+            TimelockAction::RevokeRole { user: Pubkey::new_unique(), role: Role::Guardian },
+            TimelockAction::UpdateFeeConfig { fee_bps: 250, fee_exempt_roles: vec![Role::Creator] },
+            TimelockAction::Unpause { global: true, subsystems: 0 },
+            TimelockAction::UpdateTokenPermissions {
+                token_info,
+                permissions: TokenPermissions {
+                    can_mint: true,
+                    can_burn: true,
+                    can_freeze: false,
+                    can_modify_metadata: false,
+                },
+            },
+            TimelockAction::UpdateDistributionRules { token_info, rules: rules(Some(100), Some(10)) },
+            TimelockAction::UpdateDelay { delay: MIN_TIMELOCK_DELAY },
+            TimelockAction::SetTreasury { mint: Pubkey::new_unique(), treasury: None },
+            TimelockAction::TransferTokenAuthority { token_info, new_authority: Some(Pubkey::new_unique()) },
+            TimelockAction::RenounceTokenAuthority { token_info },
+        ];
+        for action in &valid {
+            assert!(validate_action(action).is_ok());
+        }
+
+        let invalid = [
+            TimelockAction::UpdateFeeConfig { fee_bps: u16::MAX, fee_exempt_roles: Vec::new() },
+            TimelockAction::UpdateDistributionRules { token_info, rules: rules(None, Some(10)) },
+            TimelockAction::UpdateDelay { delay: MIN_TIMELOCK_DELAY - 1 },
+            TimelockAction::UpdateDelay { delay: MAX_TIMELOCK_DELAY + 1 },
+        ];
+        for action in &invalid {
+            assert!(validate_action(action).is_err());
+        }
+    }
+
+    #[test]
+    fn direct_changes_close_once_the_queue_exists() {
+        let (key, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (mut lamports, mut data) = (0, Vec::new());
+        let missing = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+        assert!(require_no_timelock(&missing).is_ok());
+
+        let (mut lamports, mut data) = (1, vec![0; TimelockQueue::LEN]);
+        let created = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+        assert_eq!(require_no_timelock(&created), Err(error!(TimelockError::TimelockRequired)));
+    }
+}
@@ -1,13 +1,36 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Token, TokenAccount, Mint};
+use anchor_lang::solana_program::program_option::COption;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::{self, spl_token::instruction::AuthorityType, Mint, Token, TokenAccount};
+use crate::access_control::{
+    require_investor, require_role, AccessControl, AccessControlError, ProjectInvestor, Role,
+};
+use crate::platform_instructions::*;
+use crate::timelock_standards::require_no_timelock;
 use crate::token_standards::*;
+use crate::{Project, Resource, ResourceError, ResourceStatus};
 
 #[derive(Accounts)]
 pub struct InitializeToken<'info> {
-    #[account(init, payer = authority, space = 8 + 32 + 200)]
+    #[account(
+        init,
+        payer = authority,
+        space = TokenInfo::LEN,
+        seeds = [b"token_info", mint.key().as_ref()],
+        bump
+    )]
     pub token_info: Account<'info, TokenInfo>,
-    #[account(mut)]
+    #[account(
+        mut,
+        constraint = mint.mint_authority == COption::Some(authority.key()) @ TokenError::UnauthorizedOperation,
+        constraint = mint.freeze_authority == COption::Some(authority.key()) @ TokenError::UnauthorizedOperation,
+        constraint = mint.supply == 0 @ TokenError::UnauthorizedOperation
+    )]
     pub mint: Account<'info, Mint>,
+    // Links the token to a project so its investor allowlist applies
+    #[account(constraint = project.creator == authority.key() @ TokenError::UnauthorizedOperation)]
+    pub project: Option<Account<'info, Project>>,
+    pub platform: TokensLive<'info>,
     #[account(mut)]
     pub authority: Signer<'info>,
     pub token_program: Program<'info, Token>,
@@ -16,14 +39,20 @@ pub struct InitializeToken<'info> {
 
 #[derive(Accounts)]
 pub struct MintToken<'info> {
-    #[account(mut)]
+    #[account(mut, has_one = mint)]
     pub token_info: Account<'info, TokenInfo>,
     #[account(mut)]
     pub mint: Account<'info, Mint>,
-    #[account(mut)]
+    #[account(mut, address = holder.token_account)]
     pub to: Account<'info, TokenAccount>,
-    #[account(mut)]
+    #[account(
+        mut,
+        has_one = token_info,
+        seeds = [b"holder", token_info.key().as_ref(), holder.owner.as_ref()],
+        bump = holder.bump
+    )]
     pub holder: Account<'info, TokenHolder>,
+    pub platform: TokensLive<'info>,
     #[account(mut)]
     pub authority: Signer<'info>,
     pub token_program: Program<'info, Token>,
@@ -31,14 +60,20 @@ pub struct MintToken<'info> {
 
 #[derive(Accounts)]
 pub struct BurnToken<'info> {
-    #[account(mut)]
+    #[account(mut, has_one = mint)]
     pub token_info: Account<'info, TokenInfo>,
     #[account(mut)]
     pub mint: Account<'info, Mint>,
-    #[account(mut)]
+    #[account(mut, address = holder.token_account)]
     pub from: Account<'info, TokenAccount>,
-    #[account(mut)]
+    #[account(
+        mut,
+        has_one = token_info,
+        seeds = [b"holder", token_info.key().as_ref(), holder.owner.as_ref()],
+        bump = holder.bump
+    )]
     pub holder: Account<'info, TokenHolder>,
+    pub platform: TokensLive<'info>,
     #[account(mut)]
     pub authority: Signer<'info>,
     pub token_program: Program<'info, Token>,
@@ -46,27 +81,538 @@ pub struct BurnToken<'info> {
 
 #[derive(Accounts)]
 pub struct TransferToken<'info> {
-    pub token_info: Account<'info, TokenInfo>,
     #[account(mut)]
+    pub token_info: Account<'info, TokenInfo>,
+    #[account(address = token_info.mint)]
+    pub mint: Account<'info, Mint>,
+    #[account(mut, address = from_holder.token_account)]
     pub from: Account<'info, TokenAccount>,
-    #[account(mut)]
+    #[account(mut, address = to_holder.token_account)]
     pub to: Account<'info, TokenAccount>,
-    #[account(mut)]
+    #[account(
+        mut,
+        has_one = token_info,
+        seeds = [b"holder", token_info.key().as_ref(), from_holder.owner.as_ref()],
+        bump = from_holder.bump
+    )]
     pub from_holder: Account<'info, TokenHolder>,
-    #[account(mut)]
+    #[account(
+        mut,
+        has_one = token_info,
+        seeds = [b"holder", token_info.key().as_ref(), to_holder.owner.as_ref()],
+        bump = to_holder.bump,
+        constraint = to_holder.key() != from_holder.key() @ TokenError::UnauthorizedOperation
+    )]
     pub to_holder: Account<'info, TokenHolder>,
+    // Required for tokens that only verified investors may receive
+    #[account(seeds = [b"access_control"], bump)]
+    pub access_control: Option<Account<'info, AccessControl>>,
+    // Alternative to the Investor role for tokens linked to a project
+    #[account(
+        seeds = [b"project_investor", investor_approval.project.as_ref(), investor_approval.investor.as_ref()],
+        bump = investor_approval.bump
+    )]
+    pub investor_approval: Option<Account<'info, ProjectInvestor>>,
+    // Required for tokens whose config requires_auth
+    #[account(
+        mut,
+        has_one = token_info,
+        seeds = [b"transfer_auth", token_info.key().as_ref(), from_holder.owner.as_ref()],
+        bump = from_authorization.bump
+    )]
+    pub from_authorization: Option<Account<'info, TransferAuthorization>>,
+    #[account(
+        mut,
+        has_one = token_info,
+        seeds = [b"transfer_auth", token_info.key().as_ref(), to_holder.owner.as_ref()],
+        bump = to_authorization.bump
+    )]
+    pub to_authorization: Option<Account<'info, TransferAuthorization>>,
+    pub platform: TokensLive<'info>,
     #[account(mut)]
     pub authority: Signer<'info>,
     pub token_program: Program<'info, Token>,
 }
 
+#[derive(Accounts)]
+#[instruction(holder: Pubkey)]
+pub struct IssueTransferAuthorization<'info> {
+    pub token_info: Account<'info, TokenInfo>,
+    #[account(
+        init_if_needed,
+        payer = issuer,
+        space = TransferAuthorization::LEN,
+        seeds = [b"transfer_auth", token_info.key().as_ref(), holder.as_ref()],
+        bump
+    )]
+    pub authorization: Account<'info, TransferAuthorization>,
+    #[account(seeds = [b"access_control"], bump)]
+    pub access_control: Account<'info, AccessControl>,
+    pub platform: TokensLive<'info>,
+    #[account(mut)]
+    pub issuer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeTransferAuthorization<'info> {
+    pub token_info: Account<'info, TokenInfo>,
+    #[account(
+        mut,
+        close = payer,
+        has_one = token_info,
+        has_one = payer,
+        seeds = [b"transfer_auth", token_info.key().as_ref(), authorization.holder.as_ref()],
+        bump = authorization.bump
+    )]
+    pub authorization: Account<'info, TransferAuthorization>,
+    #[account(seeds = [b"access_control"], bump)]
+    pub access_control: Account<'info, AccessControl>,
+    pub platform: TokensLive<'info>,
+    pub issuer: Signer<'info>,
+    /// CHECK: Receives the rent of whoever paid for the authorization
+    #[account(mut)]
+    pub payer: AccountInfo<'info>,
+}
+
 #[derive(Accounts)]
 pub struct UpdateVesting<'info> {
     pub token_info: Account<'info, TokenInfo>,
+    #[account(mut, has_one = token_info)]
+    pub holder: Account<'info, TokenHolder>,
+    pub platform: TokensLive<'info>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseVested<'info> {
+    pub token_info: Account<'info, TokenInfo>,
+    #[account(
+        mut,
+        has_one = token_info,
+        has_one = owner,
+        seeds = [b"holder", token_info.key().as_ref(), owner.key().as_ref()],
+        bump = holder.bump
+    )]
+    pub holder: Account<'info, TokenHolder>,
+    pub platform: TokensLive<'info>,
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct OpenHolder<'info> {
+    pub token_info: Account<'info, TokenInfo>,
+    #[account(
+        init,
+        payer = owner,
+        space = TokenHolder::LEN,
+        seeds = [b"holder", token_info.key().as_ref(), owner.key().as_ref()],
+        bump
+    )]
+    pub holder: Account<'info, TokenHolder>,
+    #[account(mut, token::mint = token_info.mint, token::authority = owner)]
+    pub token_account: Account<'info, TokenAccount>,
+    #[account(address = token_info.mint)]
+    pub mint: Account<'info, Mint>,
+    pub platform: TokensLive<'info>,
     #[account(mut)]
+    pub owner: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseHolder<'info> {
+    pub token_info: Account<'info, TokenInfo>,
+    #[account(
+        mut,
+        close = owner,
+        has_one = owner,
+        has_one = token_info,
+        seeds = [b"holder", token_info.key().as_ref(), owner.key().as_ref()],
+        bump = holder.bump
+    )]
     pub holder: Account<'info, TokenHolder>,
+    #[account(mut, address = holder.token_account)]
+    pub token_account: Account<'info, TokenAccount>,
+    #[account(address = token_info.mint)]
+    pub mint: Account<'info, Mint>,
+    pub platform: TokensLive<'info>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ReconcileHolder<'info> {
+    #[account(mut)]
+    pub token_info: Account<'info, TokenInfo>,
+    #[account(
+        mut,
+        has_one = token_info,
+        seeds = [b"holder", token_info.key().as_ref(), holder.owner.as_ref()],
+        bump = holder.bump
+    )]
+    pub holder: Account<'info, TokenHolder>,
+    #[account(address = holder.token_account)]
+    pub token_account: Account<'info, TokenAccount>,
+    pub platform: TokensLive<'info>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AuditSupply<'info> {
+    #[account(mut, has_one = mint)]
+    pub token_info: Account<'info, TokenInfo>,
+    pub mint: Account<'info, Mint>,
+    pub platform: TokensLive<'info>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CreateCollection<'info> {
+    #[account(
+        init,
+        payer = creator,
+        space = Collection::LEN,
+        seeds = [b"collection", project.key().as_ref()],
+        bump
+    )]
+    pub collection: Account<'info, Collection>,
+    #[account(has_one = creator)]
+    pub project: Account<'info, Project>,
+    pub platform: TokensLive<'info>,
+    #[account(mut)]
+    pub creator: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MintNft<'info> {
+    #[account(
+        mut,
+        seeds = [b"collection", collection.project.as_ref()],
+        bump = collection.bump
+    )]
+    pub collection: Account<'info, Collection>,
+    #[account(
+        address = collection.project,
+        constraint = project.creator == authority.key() @ TokenError::UnauthorizedOperation
+    )]
+    pub project: Account<'info, Project>,
+    #[account(
+        init,
+        payer = authority,
+        space = NftItem::LEN,
+        seeds = [b"nft", mint.key().as_ref()],
+        bump
+    )]
+    pub item: Account<'info, NftItem>,
+    #[account(
+        init,
+        payer = authority,
+        mint::decimals = 0,
+        mint::authority = authority,
+        mint::freeze_authority = authority
+    )]
+    pub mint: Account<'info, Mint>,
+    /// CHECK: Any wallet may receive the NFT
+    pub recipient: AccountInfo<'info>,
+    #[account(
+        init,
+        payer = authority,
+        associated_token::mint = mint,
+        associated_token::authority = recipient
+    )]
+    pub recipient_token_account: Account<'info, TokenAccount>,
+    pub platform: TokensLive<'info>,
     #[account(mut)]
     pub authority: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+#[instruction(token_type: TokenType, shares: u64, decimals: u8)]
+pub struct TokenizeResource<'info> {
+    #[account(mut, has_one = provider)]
+    pub resource: Account<'info, Resource>,
+    #[account(
+        init,
+        payer = provider,
+        space = ResourceToken::LEN,
+        seeds = [b"resource_token", resource.key().as_ref()],
+        bump
+    )]
+    pub resource_token: Account<'info, ResourceToken>,
+    #[account(
+        init,
+        payer = provider,
+        seeds = [b"resource_mint", resource.key().as_ref()],
+        bump,
+        mint::decimals = decimals,
+        mint::authority = resource_token
+    )]
+    pub mint: Account<'info, Mint>,
+    #[account(
+        init,
+        payer = provider,
+        associated_token::mint = mint,
+        associated_token::authority = provider
+    )]
+    pub provider_token_account: Account<'info, TokenAccount>,
+    pub platform: TokensLive<'info>,
+    #[account(mut)]
+    pub provider: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct RedeemResource<'info> {
+    #[account(mut)]
+    pub resource: Account<'info, Resource>,
+    #[account(
+        mut,
+        has_one = resource,
+        has_one = mint,
+        seeds = [b"resource_token", resource.key().as_ref()],
+        bump = resource_token.bump
+    )]
+    pub resource_token: Account<'info, ResourceToken>,
+    #[account(mut)]
+    pub mint: Account<'info, Mint>,
+    #[account(mut, token::mint = mint, token::authority = holder)]
+    pub holder_token_account: Account<'info, TokenAccount>,
+    pub platform: TokensLive<'info>,
+    pub holder: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ListTokens<'info> {
+    #[account(
+        init,
+        payer = seller,
+        space = Listing::LEN,
+        seeds = [b"listing", mint.key().as_ref(), seller.key().as_ref()],
+        bump
+    )]
+    pub listing: Account<'info, Listing>,
+    #[account(
+        has_one = mint,
+        seeds = [b"token_info", mint.key().as_ref()],
+        bump = token_info.bump
+    )]
+    pub token_info: Account<'info, TokenInfo>,
+    pub mint: Account<'info, Mint>,
+    pub payment_mint: Account<'info, Mint>,
+    #[account(
+        has_one = token_info,
+        seeds = [b"holder", token_info.key().as_ref(), seller.key().as_ref()],
+        bump = seller_holder.bump
+    )]
+    pub seller_holder: Account<'info, TokenHolder>,
+    #[account(mut, address = seller_holder.token_account)]
+    pub seller_token_account: Account<'info, TokenAccount>,
+    pub platform: TokensLive<'info>,
+    #[account(mut)]
+    pub seller: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ListResourceTokens<'info> {
+    #[account(
+        init,
+        payer = seller,
+        space = Listing::LEN,
+        seeds = [b"listing", mint.key().as_ref(), seller.key().as_ref()],
+        bump
+    )]
+    pub listing: Account<'info, Listing>,
+    #[account(
+        has_one = mint,
+        seeds = [b"resource_token", resource_token.resource.as_ref()],
+        bump = resource_token.bump
+    )]
+    pub resource_token: Account<'info, ResourceToken>,
+    pub mint: Account<'info, Mint>,
+    pub payment_mint: Account<'info, Mint>,
+    #[account(mut, token::mint = mint, token::authority = seller)]
+    pub seller_token_account: Account<'info, TokenAccount>,
+    pub platform: TokensLive<'info>,
+    #[account(mut)]
+    pub seller: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct BuyListing<'info> {
+    #[account(
+        mut,
+        seeds = [b"listing", listing.mint.as_ref(), listing.seller.as_ref()],
+        bump = listing.bump,
+        constraint = listing.token_info == Some(token_info.key()) @ TokenError::UnauthorizedOperation
+    )]
+    pub listing: Account<'info, Listing>,
+    #[account(
+        mut,
+        seeds = [b"token_info", listing.mint.as_ref()],
+        bump = token_info.bump
+    )]
+    pub token_info: Account<'info, TokenInfo>,
+    #[account(address = listing.mint)]
+    pub mint: Account<'info, Mint>,
+    #[account(
+        mut,
+        has_one = token_info,
+        seeds = [b"holder", token_info.key().as_ref(), listing.seller.as_ref()],
+        bump = seller_holder.bump
+    )]
+    pub seller_holder: Account<'info, TokenHolder>,
+    #[account(
+        mut,
+        has_one = token_info,
+        seeds = [b"holder", token_info.key().as_ref(), buyer.key().as_ref()],
+        bump = buyer_holder.bump
+    )]
+    pub buyer_holder: Account<'info, TokenHolder>,
+    #[account(mut, address = listing.token_account)]
+    pub seller_token_account: Account<'info, TokenAccount>,
+    #[account(mut, address = buyer_holder.token_account)]
+    pub buyer_token_account: Account<'info, TokenAccount>,
+    // Required for tokens that only verified investors may receive
+    #[account(seeds = [b"access_control"], bump)]
+    pub access_control: Option<Account<'info, AccessControl>>,
+    // Alternative to the Investor role for tokens linked to a project
+    #[account(
+        seeds = [b"project_investor", investor_approval.project.as_ref(), investor_approval.investor.as_ref()],
+        bump = investor_approval.bump
+    )]
+    pub investor_approval: Option<Account<'info, ProjectInvestor>>,
+    // Required for tokens whose config requires_auth
+    #[account(
+        mut,
+        has_one = token_info,
+        seeds = [b"transfer_auth", token_info.key().as_ref(), listing.seller.as_ref()],
+        bump = seller_authorization.bump
+    )]
+    pub seller_authorization: Option<Account<'info, TransferAuthorization>>,
+    #[account(
+        mut,
+        has_one = token_info,
+        seeds = [b"transfer_auth", token_info.key().as_ref(), buyer.key().as_ref()],
+        bump = buyer_authorization.bump
+    )]
+    pub buyer_authorization: Option<Account<'info, TransferAuthorization>>,
+    #[account(mut, token::mint = listing.payment_mint, token::authority = buyer)]
+    pub buyer_payment_account: Account<'info, TokenAccount>,
+    #[account(mut, token::mint = listing.payment_mint, token::authority = listing.seller)]
+    pub seller_payment_account: Account<'info, TokenAccount>,
+    // Required when the listing carries a royalty
+    #[account(mut, token::mint = listing.payment_mint)]
+    pub royalty_payment_account: Option<Account<'info, TokenAccount>>,
+    pub platform: TokensLive<'info>,
+    pub buyer: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct BuyResourceListing<'info> {
+    #[account(
+        mut,
+        seeds = [b"listing", listing.mint.as_ref(), listing.seller.as_ref()],
+        bump = listing.bump,
+        constraint = listing.token_info.is_none() @ TokenError::UnauthorizedOperation
+    )]
+    pub listing: Account<'info, Listing>,
+    #[account(mut, address = listing.token_account)]
+    pub seller_token_account: Account<'info, TokenAccount>,
+    #[account(mut, token::mint = listing.mint, token::authority = buyer)]
+    pub buyer_token_account: Account<'info, TokenAccount>,
+    #[account(mut, token::mint = listing.payment_mint, token::authority = buyer)]
+    pub buyer_payment_account: Account<'info, TokenAccount>,
+    #[account(mut, token::mint = listing.payment_mint, token::authority = listing.seller)]
+    pub seller_payment_account: Account<'info, TokenAccount>,
+    // Required when the listing carries a royalty
+    #[account(mut, token::mint = listing.payment_mint)]
+    pub royalty_payment_account: Option<Account<'info, TokenAccount>>,
+    pub platform: TokensLive<'info>,
+    pub buyer: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+// Open while tokens are paused, so sellers can always withdraw a listing
+#[derive(Accounts)]
+pub struct CancelListing<'info> {
+    #[account(
+        mut,
+        close = seller,
+        has_one = seller,
+        seeds = [b"listing", listing.mint.as_ref(), seller.key().as_ref()],
+        bump = listing.bump
+    )]
+    pub listing: Account<'info, Listing>,
+    #[account(mut, address = listing.token_account)]
+    pub seller_token_account: Account<'info, TokenAccount>,
+    // Required for project token listings, whose seller account is frozen
+    #[account(
+        seeds = [b"token_info", listing.mint.as_ref()],
+        bump = token_info.bump
+    )]
+    pub token_info: Option<Account<'info, TokenInfo>>,
+    #[account(address = listing.mint)]
+    pub mint: Option<Account<'info, Mint>>,
+    #[account(mut)]
+    pub seller: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct TransferTokenAuthority<'info> {
+    #[account(mut, has_one = authority)]
+    pub token_info: Account<'info, TokenInfo>,
+    /// CHECK: Timelock queue PDA, only checked for existence
+    #[account(seeds = [b"timelock_queue"], bump)]
+    pub timelock_queue: UncheckedAccount<'info>,
+    pub platform: TokensLive<'info>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptTokenAuthority<'info> {
+    #[account(mut)]
+    pub token_info: Account<'info, TokenInfo>,
+    pub platform: TokensLive<'info>,
+    pub new_authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RenounceTokenAuthority<'info> {
+    #[account(
+        mut,
+        has_one = mint,
+        has_one = authority,
+        seeds = [b"token_info", mint.key().as_ref()],
+        bump = token_info.bump
+    )]
+    pub token_info: Account<'info, TokenInfo>,
+    #[account(mut)]
+    pub mint: Account<'info, Mint>,
+    /// CHECK: Timelock queue PDA, only checked for existence
+    #[account(seeds = [b"timelock_queue"], bump)]
+    pub timelock_queue: UncheckedAccount<'info>,
+    pub platform: TokensLive<'info>,
+    pub authority: Signer<'info>,
+    pub token_program: Program<'info, Token>,
 }
 
 // Token instruction handlers
@@ -77,14 +623,41 @@ pub fn handle_initialize_token(
     distribution_rules: DistributionRules,
     permissions: TokenPermissions,
 ) -> Result<()> {
+    // On-chain mint must agree with the declared metadata
+    if ctx.accounts.mint.decimals != metadata.decimals {
+        return Err(error!(TokenError::InvalidTokenType));
+    }
+
     let token_info = &mut ctx.accounts.token_info;
+    token_info.mint = ctx.accounts.mint.key();
+    token_info.project = ctx.accounts.project.as_ref().map(|project| project.key());
+    token_info.bump = *ctx.bumps.get("token_info").unwrap();
     token_info.initialize(
         metadata,
         config,
         distribution_rules,
         permissions,
         ctx.accounts.authority.key(),
-    )
+    )?;
+
+    // The token info PDA takes over the SPL mint authority so every mint
+    // goes through the supply checks in TokenInfo::mint, and the freeze
+    // authority so holder accounts can be kept frozen between transfers
+    for authority_type in [AuthorityType::MintTokens, AuthorityType::FreezeAccount] {
+        token::set_authority(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::SetAuthority {
+                    current_authority: ctx.accounts.authority.to_account_info(),
+                    account_or_mint: ctx.accounts.mint.to_account_info(),
+                },
+            ),
+            authority_type,
+            Some(token_info.key()),
+        )?;
+    }
+
+    Ok(())
 }
 
 pub fn handle_mint_token(
@@ -102,18 +675,43 @@ pub fn handle_mint_token(
     // Mint tokens
     token_info.mint(amount, holder)?;
 
+    let mint_key = ctx.accounts.mint.key();
+    let seeds = &[b"token_info", mint_key.as_ref(), &[token_info.bump]];
+    let signer = &[&seeds[..]];
+
     // Create mint CPI
+    thaw_holder_account(token_info, &ctx.accounts.mint, &ctx.accounts.to, &ctx.accounts.token_program)?;
     token::mint_to(
-        CpiContext::new(
+        CpiContext::new_with_signer(
             ctx.accounts.token_program.to_account_info(),
             token::MintTo {
                 mint: ctx.accounts.mint.to_account_info(),
                 to: ctx.accounts.to.to_account_info(),
-                authority: ctx.accounts.authority.to_account_info(),
+                authority: token_info.to_account_info(),
             },
+            signer,
         ),
         amount,
     )?;
+    freeze_holder_account(token_info, &ctx.accounts.mint, &ctx.accounts.to, &ctx.accounts.token_program)?;
+
+    // A fully minted NFT can never be minted again
+    if token_info.metadata.token_type == TokenType::NFT
+        && Some(token_info.total_supply) == token_info.config.max_supply
+    {
+        token::set_authority(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::SetAuthority {
+                    current_authority: token_info.to_account_info(),
+                    account_or_mint: ctx.accounts.mint.to_account_info(),
+                },
+                signer,
+            ),
+            AuthorityType::MintTokens,
+            None,
+        )?;
+    }
 
     Ok(())
 }
@@ -135,6 +733,7 @@ pub fn handle_burn_token(
     token_info.burn(amount, holder)?;
 
     // Create burn CPI
+    thaw_holder_account(token_info, &ctx.accounts.mint, &ctx.accounts.from, &ctx.accounts.token_program)?;
     token::burn(
         CpiContext::new(
             ctx.accounts.token_program.to_account_info(),
@@ -146,6 +745,7 @@ pub fn handle_burn_token(
         ),
         amount,
     )?;
+    freeze_holder_account(token_info, &ctx.accounts.mint, &ctx.accounts.from, &ctx.accounts.token_program)?;
 
     Ok(())
 }
@@ -154,7 +754,7 @@ pub fn handle_transfer_token(
     ctx: Context<TransferToken>,
     amount: u64,
 ) -> Result<()> {
-    let token_info = &ctx.accounts.token_info;
+    let token_info = &mut ctx.accounts.token_info;
     let from_holder = &mut ctx.accounts.from_holder;
     let to_holder = &mut ctx.accounts.to_holder;
 
@@ -163,10 +763,29 @@ pub fn handle_transfer_token(
         return Err(error!(TokenError::UnauthorizedOperation));
     }
 
+    require_restricted_receiver(
+        token_info,
+        ctx.accounts.access_control.as_deref(),
+        ctx.accounts.investor_approval.as_deref(),
+        &to_holder.owner,
+    )?;
+
     // Transfer tokens
-    token_info.transfer(amount, from_holder, to_holder)?;
+    let authorizations = ctx.accounts.from_authorization.as_deref_mut()
+        .zip(ctx.accounts.to_authorization.as_deref_mut());
+    token_info.transfer(
+        amount,
+        from_holder,
+        to_holder,
+        authorizations,
+        Clock::get()?.unix_timestamp,
+    )?;
 
     // Create transfer CPI
+    let accounts = [&ctx.accounts.from, &ctx.accounts.to];
+    for account in accounts {
+        thaw_holder_account(token_info, &ctx.accounts.mint, account, &ctx.accounts.token_program)?;
+    }
     token::transfer(
         CpiContext::new(
             ctx.accounts.token_program.to_account_info(),
@@ -178,10 +797,90 @@ pub fn handle_transfer_token(
         ),
         amount,
     )?;
+    for account in accounts {
+        freeze_holder_account(token_info, &ctx.accounts.mint, account, &ctx.accounts.token_program)?;
+    }
+
+    Ok(())
+}
+
+// The token authority or a compliance officer approves a holder of a
+// restricted token; reissuing replaces the terms and resets the period
+pub fn handle_issue_transfer_authorization(
+    ctx: Context<IssueTransferAuthorization>,
+    holder: Pubkey,
+    expires_at: Option<i64>,
+    period_limit: Option<u64>,
+    period_length: i64,
+) -> Result<()> {
+    let issuer = ctx.accounts.issuer.key();
+    require_transfer_issuer(&ctx.accounts.token_info, &ctx.accounts.access_control, &issuer)?;
+    if period_limit.is_some() && period_length <= 0 {
+        return Err(error!(TokenError::InvalidTransferLimit));
+    }
+
+    let authorization = &mut ctx.accounts.authorization;
+    authorization.record_payer(issuer);
+    authorization.token_info = ctx.accounts.token_info.key();
+    authorization.holder = holder;
+    authorization.issued_by = issuer;
+    authorization.expires_at = expires_at;
+    authorization.period_limit = period_limit;
+    authorization.period_length = period_length;
+    authorization.period_start = Clock::get()?.unix_timestamp;
+    authorization.period_used = 0;
+    authorization.bump = *ctx.bumps.get("authorization").unwrap();
+
+    emit!(TransferAuthorized {
+        token_info: authorization.token_info,
+        holder,
+        issued_by: issuer,
+        expires_at,
+        period_limit,
+    });
 
     Ok(())
 }
 
+pub fn handle_revoke_transfer_authorization(
+    ctx: Context<RevokeTransferAuthorization>,
+) -> Result<()> {
+    require_transfer_issuer(
+        &ctx.accounts.token_info,
+        &ctx.accounts.access_control,
+        &ctx.accounts.issuer.key(),
+    )
+}
+
+// Restricted tokens may only move to verified investors: holders of the
+// Investor role, or investors approved by the token's project
+fn require_restricted_receiver(
+    token_info: &TokenInfo,
+    access_control: Option<&AccessControl>,
+    approval: Option<&ProjectInvestor>,
+    receiver: &Pubkey,
+) -> Result<()> {
+    if !token_info.config.requires_auth {
+        return Ok(());
+    }
+    match (token_info.project, access_control) {
+        (Some(project), _) => require_investor(access_control, approval, &project, receiver),
+        (None, Some(access_control)) => require_role(access_control, receiver, &Role::Investor),
+        (None, None) => Err(error!(AccessControlError::UnauthorizedRole)),
+    }
+}
+
+fn require_transfer_issuer(
+    token_info: &TokenInfo,
+    access_control: &AccessControl,
+    issuer: &Pubkey,
+) -> Result<()> {
+    if *issuer != token_info.authority && !access_control.has_role(issuer, &Role::Compliance) {
+        return Err(error!(TokenError::UnauthorizedOperation));
+    }
+    Ok(())
+}
+
 pub fn handle_update_vesting(
     ctx: Context<UpdateVesting>,
     schedule: VestingSchedule,
@@ -195,4 +894,700 @@ pub fn handle_update_vesting(
     }
 
     token_info.update_vesting(holder, schedule)
-}
\ No newline at end of file
+}
+
+// The holder unlocks whatever their vesting schedule has released so far
+pub fn handle_release_vested(ctx: Context<ReleaseVested>) -> Result<()> {
+    let holder = &mut ctx.accounts.holder;
+    let amount = ctx.accounts.token_info.release_vested(holder, Clock::get()?.unix_timestamp)?;
+
+    emit!(VestedTokensReleased {
+        token_info: holder.token_info,
+        owner: holder.owner,
+        amount,
+    });
+
+    Ok(())
+}
+
+pub fn handle_open_holder(ctx: Context<OpenHolder>) -> Result<()> {
+    let holder = &mut ctx.accounts.holder;
+
+    holder.owner = ctx.accounts.owner.key();
+    holder.token_info = ctx.accounts.token_info.key();
+    holder.token_account = ctx.accounts.token_account.key();
+    holder.balance = 0;
+    holder.locked_balance = 0;
+    holder.vesting_schedule = None;
+    holder.last_transfer = 0;
+    holder.acquired_at = 0;
+    holder.avg_acquired_at = 0;
+    holder.bump = *ctx.bumps.get("holder").unwrap();
+
+    freeze_holder_account(
+        &ctx.accounts.token_info,
+        &ctx.accounts.mint,
+        &ctx.accounts.token_account,
+        &ctx.accounts.token_program,
+    )
+}
+
+pub fn handle_close_holder(ctx: Context<CloseHolder>) -> Result<()> {
+    // Rent is only reclaimable once nothing is held or vesting
+    if ctx.accounts.holder.total_balance() > 0 {
+        return Err(error!(TokenError::HolderNotEmpty));
+    }
+
+    // The token account goes back to its owner unfrozen
+    thaw_holder_account(
+        &ctx.accounts.token_info,
+        &ctx.accounts.mint,
+        &ctx.accounts.token_account,
+        &ctx.accounts.token_program,
+    )
+}
+
+// Compare a holder's shadow balance against its SPL token account. Anyone may
+// audit; only the token authority may resync the ledger to the SPL amount.
+pub fn handle_reconcile_holder(
+    ctx: Context<ReconcileHolder>,
+    resync: bool,
+) -> Result<()> {
+    let token_info = &mut ctx.accounts.token_info;
+    let holder = &mut ctx.accounts.holder;
+    let spl_balance = ctx.accounts.token_account.amount;
+
+    if resync && ctx.accounts.authority.key() != token_info.authority {
+        return Err(error!(TokenError::UnauthorizedOperation));
+    }
+
+    if let Some(ledger_balance) = TokenInfo::holder_discrepancy(holder, spl_balance) {
+        if resync {
+            token_info.resync_holder(holder, spl_balance)?;
+        }
+
+        emit!(HolderDiscrepancy {
+            token_info: token_info.key(),
+            holder: holder.key(),
+            ledger_balance,
+            spl_balance,
+            resynced: resync,
+        });
+    }
+
+    Ok(())
+}
+
+// Compare the shadow total supply against the SPL mint supply
+pub fn handle_audit_supply(
+    ctx: Context<AuditSupply>,
+    resync: bool,
+) -> Result<()> {
+    let token_info = &mut ctx.accounts.token_info;
+    let spl_supply = ctx.accounts.mint.supply;
+
+    if resync && ctx.accounts.authority.key() != token_info.authority {
+        return Err(error!(TokenError::UnauthorizedOperation));
+    }
+
+    if let Some(ledger_supply) = token_info.supply_discrepancy(spl_supply) {
+        if resync {
+            token_info.resync_supply(spl_supply);
+        }
+
+        emit!(SupplyDiscrepancy {
+            token_info: token_info.key(),
+            ledger_supply,
+            spl_supply,
+            resynced: resync,
+        });
+    }
+
+    Ok(())
+}
+
+pub fn handle_create_collection(
+    ctx: Context<CreateCollection>,
+    name: String,
+    symbol: String,
+    uri: String,
+) -> Result<()> {
+    validate_metadata_fields(&name, &symbol, &uri)?;
+
+    let collection = &mut ctx.accounts.collection;
+    collection.project = ctx.accounts.project.key();
+    collection.name = name;
+    collection.symbol = symbol;
+    collection.uri = uri;
+    collection.item_count = 0;
+    collection.bump = *ctx.bumps.get("collection").unwrap();
+
+    Ok(())
+}
+
+pub fn handle_mint_nft(
+    ctx: Context<MintNft>,
+    uri: String,
+) -> Result<()> {
+    if uri.len() > MAX_URI_LEN {
+        return Err(error!(TokenError::MetadataTooLong));
+    }
+
+    let collection = &mut ctx.accounts.collection;
+    let item = &mut ctx.accounts.item;
+
+    item.collection = collection.key();
+    item.mint = ctx.accounts.mint.key();
+    item.index = collection.item_count;
+    item.uri = uri;
+    item.created_at = Clock::get()?.unix_timestamp;
+    item.bump = *ctx.bumps.get("item").unwrap();
+
+    collection.item_count = collection.item_count.checked_add(1)
+        .ok_or(TokenError::SupplyExceeded)?;
+
+    // Mint the single unit
+    token::mint_to(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            token::MintTo {
+                mint: ctx.accounts.mint.to_account_info(),
+                to: ctx.accounts.recipient_token_account.to_account_info(),
+                authority: ctx.accounts.authority.to_account_info(),
+            },
+        ),
+        1,
+    )?;
+
+    // Remove the mint authority so supply is fixed at one
+    token::set_authority(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            token::SetAuthority {
+                current_authority: ctx.accounts.authority.to_account_info(),
+                account_or_mint: ctx.accounts.mint.to_account_info(),
+            },
+        ),
+        AuthorityType::MintTokens,
+        None,
+    )?;
+
+    Ok(())
+}
+
+pub fn handle_tokenize_resource(
+    ctx: Context<TokenizeResource>,
+    token_type: TokenType,
+    shares: u64,
+    decimals: u8,
+    royalty: Option<RoyaltyConfig>,
+) -> Result<()> {
+    match token_type {
+        TokenType::NFT if shares == 1 && decimals == 0 => {}
+        TokenType::ResourceToken if shares > 0 => {}
+        _ => return Err(error!(TokenError::InvalidTokenType)),
+    }
+    if let Some(royalty) = &royalty {
+        royalty.validate()?;
+    }
+
+    let resource = &mut ctx.accounts.resource;
+    let resource_token = &mut ctx.accounts.resource_token;
+
+    resource.status.ensure_transition(&ResourceStatus::Tokenized)?;
+    resource.status = ResourceStatus::Tokenized;
+
+    resource_token.resource = resource.key();
+    resource_token.mint = ctx.accounts.mint.key();
+    resource_token.token_type = token_type;
+    resource_token.total_shares = shares;
+    resource_token.redeemed_shares = 0;
+    resource_token.created_at = Clock::get()?.unix_timestamp;
+    resource_token.royalty = royalty;
+    resource_token.bump = *ctx.bumps.get("resource_token").unwrap();
+
+    let resource_key = resource.key();
+    let seeds = &[b"resource_token", resource_key.as_ref(), &[resource_token.bump]];
+    let signer = &[&seeds[..]];
+
+    // Mint every share to the provider, then fix the supply
+    token::mint_to(
+        CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            token::MintTo {
+                mint: ctx.accounts.mint.to_account_info(),
+                to: ctx.accounts.provider_token_account.to_account_info(),
+                authority: resource_token.to_account_info(),
+            },
+            signer,
+        ),
+        shares,
+    )?;
+
+    token::set_authority(
+        CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            token::SetAuthority {
+                current_authority: resource_token.to_account_info(),
+                account_or_mint: ctx.accounts.mint.to_account_info(),
+            },
+            signer,
+        ),
+        AuthorityType::MintTokens,
+        None,
+    )?;
+
+    emit!(ResourceTokenized {
+        resource: resource_key,
+        mint: resource_token.mint,
+        total_shares: shares,
+    });
+
+    Ok(())
+}
+
+pub fn handle_redeem_resource(
+    ctx: Context<RedeemResource>,
+    shares: u64,
+) -> Result<()> {
+    let resource = &mut ctx.accounts.resource;
+    let resource_token = &mut ctx.accounts.resource_token;
+
+    if resource.status != ResourceStatus::Tokenized {
+        return Err(error!(ResourceError::ResourceUnavailable));
+    }
+    if shares == 0 || shares > resource_token.outstanding_shares() {
+        return Err(error!(TokenError::InsufficientBalance));
+    }
+
+    // Burn the redeemed shares
+    token::burn(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            token::Burn {
+                mint: ctx.accounts.mint.to_account_info(),
+                from: ctx.accounts.holder_token_account.to_account_info(),
+                authority: ctx.accounts.holder.to_account_info(),
+            },
+        ),
+        shares,
+    )?;
+
+    resource_token.redeemed_shares = resource_token.redeemed_shares.checked_add(shares)
+        .ok_or(TokenError::SupplyExceeded)?;
+
+    let consumed = resource_token.is_fully_redeemed();
+    if consumed {
+        resource.status = ResourceStatus::Consumed;
+    }
+
+    emit!(ResourceRedeemed {
+        resource: resource.key(),
+        holder: ctx.accounts.holder.key(),
+        shares,
+        consumed,
+    });
+
+    Ok(())
+}
+
+pub fn handle_list_tokens(
+    ctx: Context<ListTokens>,
+    amount: u64,
+    price: u64,
+) -> Result<()> {
+    let token_info = &ctx.accounts.token_info;
+    if !token_info.config.is_transferable {
+        return Err(error!(TokenError::UnauthorizedOperation));
+    }
+    if amount == 0 || amount > ctx.accounts.seller_holder.balance {
+        return Err(error!(TokenError::InsufficientBalance));
+    }
+
+    let listing = &mut ctx.accounts.listing;
+    listing.seller = ctx.accounts.seller.key();
+    listing.mint = ctx.accounts.mint.key();
+    listing.payment_mint = ctx.accounts.payment_mint.key();
+    listing.token_account = ctx.accounts.seller_token_account.key();
+    listing.token_info = Some(token_info.key());
+    listing.price = price;
+    listing.remaining = amount;
+    listing.royalty = token_info.config.royalty.clone();
+    listing.created_at = Clock::get()?.unix_timestamp;
+    listing.bump = *ctx.bumps.get("listing").unwrap();
+
+    let seller_token_account = &ctx.accounts.seller_token_account;
+    thaw_holder_account(token_info, &ctx.accounts.mint, seller_token_account, &ctx.accounts.token_program)?;
+    approve_listing(
+        listing,
+        seller_token_account,
+        &ctx.accounts.seller,
+        &ctx.accounts.token_program,
+        amount,
+    )?;
+    freeze_holder_account(token_info, &ctx.accounts.mint, seller_token_account, &ctx.accounts.token_program)
+}
+
+pub fn handle_list_resource_tokens(
+    ctx: Context<ListResourceTokens>,
+    amount: u64,
+    price: u64,
+) -> Result<()> {
+    if amount == 0 || amount > ctx.accounts.seller_token_account.amount {
+        return Err(error!(TokenError::InsufficientBalance));
+    }
+
+    let listing = &mut ctx.accounts.listing;
+    listing.seller = ctx.accounts.seller.key();
+    listing.mint = ctx.accounts.mint.key();
+    listing.payment_mint = ctx.accounts.payment_mint.key();
+    listing.token_account = ctx.accounts.seller_token_account.key();
+    listing.token_info = None;
+    listing.price = price;
+    listing.remaining = amount;
+    listing.royalty = ctx.accounts.resource_token.royalty.clone();
+    listing.created_at = Clock::get()?.unix_timestamp;
+    listing.bump = *ctx.bumps.get("listing").unwrap();
+
+    approve_listing(
+        listing,
+        &ctx.accounts.seller_token_account,
+        &ctx.accounts.seller,
+        &ctx.accounts.token_program,
+        amount,
+    )
+}
+
+// Project token sales go through the same ledger checks as transfer_token
+pub fn handle_buy_listing(
+    ctx: Context<BuyListing>,
+    amount: u64,
+) -> Result<()> {
+    let token_info = &mut ctx.accounts.token_info;
+    let seller_holder = &mut ctx.accounts.seller_holder;
+    let buyer_holder = &mut ctx.accounts.buyer_holder;
+
+    if amount == 0 || amount > ctx.accounts.listing.remaining {
+        return Err(error!(TokenError::InsufficientBalance));
+    }
+
+    require_restricted_receiver(
+        token_info,
+        ctx.accounts.access_control.as_deref(),
+        ctx.accounts.investor_approval.as_deref(),
+        &buyer_holder.owner,
+    )?;
+
+    let authorizations = ctx.accounts.seller_authorization.as_deref_mut()
+        .zip(ctx.accounts.buyer_authorization.as_deref_mut());
+    token_info.transfer(
+        amount,
+        seller_holder,
+        buyer_holder,
+        authorizations,
+        Clock::get()?.unix_timestamp,
+    )?;
+
+    pay_for_listing(
+        &ctx.accounts.listing,
+        amount,
+        &ctx.accounts.buyer,
+        &ctx.accounts.buyer_payment_account,
+        &ctx.accounts.seller_payment_account,
+        ctx.accounts.royalty_payment_account.as_ref(),
+        &ctx.accounts.token_program,
+    )?;
+    let accounts = [&ctx.accounts.seller_token_account, &ctx.accounts.buyer_token_account];
+    for account in accounts {
+        thaw_holder_account(token_info, &ctx.accounts.mint, account, &ctx.accounts.token_program)?;
+    }
+    deliver_listing(
+        &ctx.accounts.listing,
+        &ctx.accounts.seller_token_account,
+        &ctx.accounts.buyer_token_account,
+        &ctx.accounts.token_program,
+        amount,
+    )?;
+    for account in accounts {
+        freeze_holder_account(token_info, &ctx.accounts.mint, account, &ctx.accounts.token_program)?;
+    }
+
+    ctx.accounts.listing.remaining -= amount;
+
+    Ok(())
+}
+
+pub fn handle_buy_resource_listing(
+    ctx: Context<BuyResourceListing>,
+    amount: u64,
+) -> Result<()> {
+    if amount == 0 || amount > ctx.accounts.listing.remaining {
+        return Err(error!(TokenError::InsufficientBalance));
+    }
+
+    pay_for_listing(
+        &ctx.accounts.listing,
+        amount,
+        &ctx.accounts.buyer,
+        &ctx.accounts.buyer_payment_account,
+        &ctx.accounts.seller_payment_account,
+        ctx.accounts.royalty_payment_account.as_ref(),
+        &ctx.accounts.token_program,
+    )?;
+    deliver_listing(
+        &ctx.accounts.listing,
+        &ctx.accounts.seller_token_account,
+        &ctx.accounts.buyer_token_account,
+        &ctx.accounts.token_program,
+        amount,
+    )?;
+
+    ctx.accounts.listing.remaining -= amount;
+
+    Ok(())
+}
+
+// Withdraw the listing's delegation over the unsold tokens
+pub fn handle_cancel_listing(ctx: Context<CancelListing>) -> Result<()> {
+    let seller_token_account = &ctx.accounts.seller_token_account;
+    let token_program = &ctx.accounts.token_program;
+    let frozen = match ctx.accounts.listing.token_info {
+        Some(_) => Some((
+            ctx.accounts.token_info.as_ref().ok_or(TokenError::MissingTokenInfo)?,
+            ctx.accounts.mint.as_ref().ok_or(TokenError::MissingTokenInfo)?,
+        )),
+        None => None,
+    };
+
+    if let Some((token_info, mint)) = frozen {
+        thaw_holder_account(token_info, mint, seller_token_account, token_program)?;
+    }
+    token::revoke(CpiContext::new(
+        token_program.to_account_info(),
+        token::Revoke {
+            source: seller_token_account.to_account_info(),
+            authority: ctx.accounts.seller.to_account_info(),
+        },
+    ))?;
+    if let Some((token_info, mint)) = frozen {
+        freeze_holder_account(token_info, mint, seller_token_account, token_program)?;
+    }
+
+    Ok(())
+}
+
+// Approve the listing as SPL delegate over the listed tokens
+fn approve_listing<'info>(
+    listing: &Account<'info, Listing>,
+    seller_token_account: &Account<'info, TokenAccount>,
+    seller: &Signer<'info>,
+    token_program: &Program<'info, Token>,
+    amount: u64,
+) -> Result<()> {
+    token::approve(
+        CpiContext::new(
+            token_program.to_account_info(),
+            token::Approve {
+                to: seller_token_account.to_account_info(),
+                delegate: listing.to_account_info(),
+                authority: seller.to_account_info(),
+            },
+        ),
+        amount,
+    )
+}
+
+// Pay the creator royalty, then the seller
+fn pay_for_listing<'info>(
+    listing: &Listing,
+    amount: u64,
+    buyer: &Signer<'info>,
+    buyer_payment_account: &Account<'info, TokenAccount>,
+    seller_payment_account: &Account<'info, TokenAccount>,
+    royalty_payment_account: Option<&Account<'info, TokenAccount>>,
+    token_program: &Program<'info, Token>,
+) -> Result<()> {
+    let gross = listing.price.checked_mul(amount)
+        .ok_or(TokenError::SupplyExceeded)?;
+    let royalty = listing.royalty.as_ref()
+        .map_or(0, |royalty| royalty.royalty_for(gross, &listing.seller, &buyer.key()));
+
+    if royalty > 0 {
+        let recipient = listing.royalty.as_ref().unwrap().recipient;
+        let royalty_account = royalty_payment_account
+            .filter(|account| account.owner == recipient)
+            .ok_or(TokenError::MissingRoyaltySource)?;
+
+        token::transfer(
+            CpiContext::new(
+                token_program.to_account_info(),
+                token::Transfer {
+                    from: buyer_payment_account.to_account_info(),
+                    to: royalty_account.to_account_info(),
+                    authority: buyer.to_account_info(),
+                },
+            ),
+            royalty,
+        )?;
+
+        emit!(RoyaltyPaid {
+            mint: listing.mint,
+            recipient,
+            seller: listing.seller,
+            buyer: buyer.key(),
+            gross,
+            royalty,
+        });
+    }
+
+    token::transfer(
+        CpiContext::new(
+            token_program.to_account_info(),
+            token::Transfer {
+                from: buyer_payment_account.to_account_info(),
+                to: seller_payment_account.to_account_info(),
+                authority: buyer.to_account_info(),
+            },
+        ),
+        gross - royalty,
+    )
+}
+
+// Deliver listed tokens from the seller under the listing's delegation
+fn deliver_listing<'info>(
+    listing: &Account<'info, Listing>,
+    from: &Account<'info, TokenAccount>,
+    to: &Account<'info, TokenAccount>,
+    token_program: &Program<'info, Token>,
+    amount: u64,
+) -> Result<()> {
+    let seeds = &[b"listing", listing.mint.as_ref(), listing.seller.as_ref(), &[listing.bump]];
+    token::transfer(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            token::Transfer {
+                from: from.to_account_info(),
+                to: to.to_account_info(),
+                authority: listing.to_account_info(),
+            },
+            &[&seeds[..]],
+        ),
+        amount,
+    )
+}
+
+// Holder token accounts stay frozen under the token info PDA, so tokens only
+// move through the instructions that apply the ledger's restrictions. Those
+// thaw the accounts involved around the SPL call and freeze them again.
+pub(crate) fn thaw_holder_account<'info>(
+    token_info: &Account<'info, TokenInfo>,
+    mint: &Account<'info, Mint>,
+    token_account: &Account<'info, TokenAccount>,
+    token_program: &Program<'info, Token>,
+) -> Result<()> {
+    let seeds = &[b"token_info", token_info.mint.as_ref(), &[token_info.bump]];
+    token::thaw_account(CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        token::ThawAccount {
+            account: token_account.to_account_info(),
+            mint: mint.to_account_info(),
+            authority: token_info.to_account_info(),
+        },
+        &[&seeds[..]],
+    ))
+}
+
+pub(crate) fn freeze_holder_account<'info>(
+    token_info: &Account<'info, TokenInfo>,
+    mint: &Account<'info, Mint>,
+    token_account: &Account<'info, TokenAccount>,
+    token_program: &Program<'info, Token>,
+) -> Result<()> {
+    let seeds = &[b"token_info", token_info.mint.as_ref(), &[token_info.bump]];
+    token::freeze_account(CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        token::FreezeAccount {
+            account: token_account.to_account_info(),
+            mint: mint.to_account_info(),
+            authority: token_info.to_account_info(),
+        },
+        &[&seeds[..]],
+    ))
+}
+
+// Drop the SPL mint authority held by the token info PDA, if it still has it
+pub(crate) fn drop_mint_authority<'info>(
+    token_info: &Account<'info, TokenInfo>,
+    mint: &Account<'info, Mint>,
+    token_program: &Program<'info, Token>,
+) -> Result<()> {
+    if mint.mint_authority != COption::Some(token_info.key()) {
+        return Ok(());
+    }
+    let seeds = &[b"token_info", token_info.mint.as_ref(), &[token_info.bump]];
+    token::set_authority(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            token::SetAuthority {
+                current_authority: token_info.to_account_info(),
+                account_or_mint: mint.to_account_info(),
+            },
+            &[&seeds[..]],
+        ),
+        AuthorityType::MintTokens,
+        None,
+    )
+}
+
+// Propose a new token authority, or cancel a pending proposal with None.
+// The SPL mint authority stays with the token info PDA throughout.
+pub fn handle_transfer_token_authority(
+    ctx: Context<TransferTokenAuthority>,
+    new_authority: Option<Pubkey>,
+) -> Result<()> {
+    require_no_timelock(&ctx.accounts.timelock_queue)?;
+    let token_info = &mut ctx.accounts.token_info;
+
+    match new_authority {
+        Some(new_authority) => token_info.start_authority_transfer(new_authority)?,
+        None => token_info.pending_authority = None,
+    }
+
+    emit!(TokenAuthorityTransferStarted {
+        token_info: token_info.key(),
+        authority: ctx.accounts.authority.key(),
+        pending_authority: token_info.pending_authority,
+    });
+
+    Ok(())
+}
+
+// The proposed authority takes over the token
+pub fn handle_accept_token_authority(ctx: Context<AcceptTokenAuthority>) -> Result<()> {
+    let token_info = &mut ctx.accounts.token_info;
+    let new_authority = ctx.accounts.new_authority.key();
+    let previous_authority = token_info.accept_authority(new_authority)?;
+
+    emit!(TokenAuthorityTransferred {
+        token_info: token_info.key(),
+        previous_authority,
+        new_authority,
+    });
+
+    Ok(())
+}
+
+// Irreversibly drop the token authority and the SPL mint authority
+pub fn handle_renounce_token_authority(ctx: Context<RenounceTokenAuthority>) -> Result<()> {
+    require_no_timelock(&ctx.accounts.timelock_queue)?;
+    let token_info = &mut ctx.accounts.token_info;
+    let previous_authority = token_info.renounce_authority()?;
+    drop_mint_authority(token_info, &ctx.accounts.mint, &ctx.accounts.token_program)?;
+
+    emit!(TokenAuthorityRenounced {
+        token_info: token_info.key(),
+        previous_authority,
+    });
+
+    Ok(())
+}
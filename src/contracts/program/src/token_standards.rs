@@ -1,5 +1,11 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Token, TokenAccount, Mint};
+use anchor_spl::token::TokenAccount;
+
+// Maximum lengths for token metadata strings
+pub const MAX_NAME_LEN: usize = 32;
+pub const MAX_SYMBOL_LEN: usize = 10;
+pub const MAX_URI_LEN: usize = 200;
+pub const MAX_ROLE_LEN: usize = 96;
 
 // Token types supported by the platform
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq)]
@@ -8,9 +14,9 @@ pub enum TokenType {
     ProjectToken,
     // Governance token with voting rights
     GovernanceToken,
-    // Token representing resource ownership
+    // Token representing (fractional) resource ownership
     ResourceToken,
-    // Token representing collaboration rights
+    // Non-transferable credential representing collaboration rights
     CollaborationToken,
     // Non-fungible token for unique assets
     NFT,
@@ -38,17 +44,133 @@ pub struct TokenConfig {
     pub is_burnable: bool,
     pub is_transferable: bool,
     pub requires_auth: bool,
+    pub royalty: Option<RoyaltyConfig>,
+}
+
+// Highest creator royalty a token may configure, 50%
+pub const MAX_ROYALTY_BPS: u16 = 5_000;
+pub const MAX_ROYALTY_EXEMPTIONS: usize = 8;
+
+// Creator royalty charged on program-mediated secondary sales
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct RoyaltyConfig {
+    pub bps: u16,
+    pub recipient: Pubkey,
+    // Upper bound on the royalty taken from a single sale
+    pub max_amount: Option<u64>,
+    // Sales where the buyer or seller is listed here pay no royalty
+    pub exempt: Vec<Pubkey>,
+}
+
+impl RoyaltyConfig {
+    pub const LEN: usize = 2 + 32 + 9 + 4 + MAX_ROYALTY_EXEMPTIONS * 32;
+
+    pub fn validate(&self) -> Result<()> {
+        if self.bps > MAX_ROYALTY_BPS || self.exempt.len() > MAX_ROYALTY_EXEMPTIONS {
+            return Err(error!(TokenError::InvalidRoyalty));
+        }
+        Ok(())
+    }
+
+    // Royalty owed on a sale of `gross`, rounded down and capped
+    pub fn royalty_for(&self, gross: u64, seller: &Pubkey, buyer: &Pubkey) -> u64 {
+        if self.exempt.contains(seller) || self.exempt.contains(buyer) {
+            return 0;
+        }
+        let royalty = (gross as u128 * self.bps as u128 / 10_000) as u64;
+        match self.max_amount {
+            Some(max_amount) => royalty.min(max_amount),
+            None => royalty,
+        }
+    }
+}
+
+// Reference point of a transfer lock
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq)]
+pub enum LockBase {
+    // The lock value is a unix timestamp
+    Absolute,
+    // The lock value is a duration from the token's created_at
+    TokenCreation,
+    // The lock value is a duration from when each holder acquired the token
+    HolderAcquisition,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct TransferLock {
+    pub base: LockBase,
+    pub value: i64,
+}
+
+impl TransferLock {
+    pub const LEN: usize = 1 + 8;
+
+    // Time from which a holder acquired at `acquired_at` may transfer
+    pub fn unlocks_at(&self, created_at: i64, acquired_at: i64) -> i64 {
+        match self.base {
+            LockBase::Absolute => self.value,
+            LockBase::TokenCreation => created_at.saturating_add(self.value),
+            LockBase::HolderAcquisition => acquired_at.saturating_add(self.value),
+        }
+    }
 }
 
-// Token distribution rules
+// Token distribution rules. Vesting and cliff periods are durations from
+// when tokens are distributed to a holder; min_hold_period is a duration
+// tokens must be held before they can be sent on.
 #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
 pub struct DistributionRules {
     pub vesting_period: Option<i64>,
     pub cliff_period: Option<i64>,
-    pub transfer_lock: Option<i64>,
+    pub transfer_lock: Option<TransferLock>,
     pub min_hold_period: Option<i64>,
 }
 
+impl DistributionRules {
+    pub const LEN: usize = 9 + 9 + (1 + TransferLock::LEN) + 9;
+
+    pub fn validate(&self) -> Result<()> {
+        let valid_vesting = match (self.vesting_period, self.cliff_period) {
+            (None, None) => true,
+            (Some(vesting), None) => vesting > 0,
+            (Some(vesting), Some(cliff)) => vesting > 0 && cliff >= 0 && cliff <= vesting,
+            // A cliff without vesting has nothing to release
+            (None, Some(_)) => false,
+        };
+        let valid_lock = self.transfer_lock.as_ref().is_none_or(|lock| lock.value > 0);
+        let valid_hold = self.min_hold_period.is_none_or(|min_hold| min_hold > 0);
+
+        if !valid_vesting || !valid_lock || !valid_hold {
+            return Err(error!(TokenError::InvalidDistributionRules));
+        }
+        Ok(())
+    }
+
+    // Vesting schedule for `amount` tokens distributed at `start`, if the
+    // rules vest distributions at all
+    pub fn vesting_schedule(&self, start: i64, amount: u64) -> Result<Option<VestingSchedule>> {
+        let Some(vesting_period) = self.vesting_period else {
+            return Ok(None);
+        };
+        let end_time = start.checked_add(vesting_period)
+            .ok_or(TokenError::InvalidVestingSchedule)?;
+        let cliff_time = start.checked_add(self.cliff_period.unwrap_or(0))
+            .ok_or(TokenError::InvalidVestingSchedule)?;
+        if end_time <= start || cliff_time > end_time {
+            return Err(error!(TokenError::InvalidVestingSchedule));
+        }
+
+        Ok(Some(VestingSchedule {
+            total_amount: amount,
+            released_amount: 0,
+            start_time: start,
+            end_time,
+            cliff_time,
+            interval: 0,
+        }))
+    }
+}
+
 // Token permissions
 #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
 pub struct TokenPermissions {
@@ -58,10 +180,13 @@ pub struct TokenPermissions {
     pub can_modify_metadata: bool,
 }
 
-// Token account
+// Token account, a PDA at ["token_info", mint] that holds the SPL mint
+// authority of its mint
 #[account]
 pub struct TokenInfo {
     pub mint: Pubkey,
+    // Project whose investor allowlist admits receivers of restricted tokens
+    pub project: Option<Pubkey>,
     pub metadata: TokenMetadata,
     pub config: TokenConfig,
     pub distribution_rules: DistributionRules,
@@ -69,16 +194,163 @@ pub struct TokenInfo {
     pub total_supply: u64,
     pub holder_count: u64,
     pub authority: Pubkey,
+    // Proposed new authority, awaiting acceptance
+    pub pending_authority: Option<Pubkey>,
+    pub bump: u8,
 }
 
-// Token holder account
+impl TokenInfo {
+    pub const LEN: usize = 8 // discriminator
+        + 32 // mint
+        + 33 // project
+        + (4 + MAX_NAME_LEN) + (4 + MAX_SYMBOL_LEN) + (4 + MAX_URI_LEN) + 1 + 1 + 32 + 1 + 8 // metadata
+        + 8 + 9 + 1 + 1 + 1 + 1 + (1 + RoyaltyConfig::LEN) // config
+        + DistributionRules::LEN // distribution_rules
+        + 4 // permissions
+        + 8 // total_supply
+        + 8 // holder_count
+        + 32 // authority
+        + 33 // pending_authority
+        + 1; // bump
+}
+
+// Token holder account, a PDA at ["holder", token_info, owner] bound to the
+// owner's SPL token account for the token's mint
 #[account]
 pub struct TokenHolder {
     pub owner: Pubkey,
+    pub token_info: Pubkey,
+    pub token_account: Pubkey,
     pub balance: u64,
     pub locked_balance: u64,
     pub vesting_schedule: Option<VestingSchedule>,
     pub last_transfer: i64,
+    // When the holder last went from holding nothing to holding tokens
+    pub acquired_at: i64,
+    // Balance-weighted average time the held tokens were received
+    pub avg_acquired_at: i64,
+    pub bump: u8,
+}
+
+impl TokenHolder {
+    pub const LEN: usize = 8 + 32 + 32 + 32 + 8 + 8 + (1 + VestingSchedule::LEN) + 8 + 8 + 8 + 1;
+
+    // Balance including tokens still locked by vesting
+    pub fn total_balance(&self) -> u64 {
+        self.balance.saturating_add(self.locked_balance)
+    }
+
+    // Stamp acquisition times after a credit: acquired_at when the holder
+    // moves off zero, and the weighted average age of the holding
+    pub fn record_acquisition(&mut self, before: u64, now: i64) {
+        let after = self.total_balance();
+        if after <= before {
+            return;
+        }
+        if before == 0 {
+            self.acquired_at = now;
+            self.avg_acquired_at = now;
+            return;
+        }
+
+        let weighted = before as i128 * self.avg_acquired_at as i128
+            + (after - before) as i128 * now as i128;
+        self.avg_acquired_at = (weighted / after as i128) as i64;
+    }
+
+    // Whether the tokens held have, on average, been held for `min_hold`
+    pub fn held_for(&self, min_hold: i64, now: i64) -> bool {
+        now.saturating_sub(self.avg_acquired_at) >= min_hold
+    }
+}
+
+// Approval for a wallet to send and receive a token whose config
+// requires_auth, PDA at ["transfer_auth", token_info, holder]
+#[account]
+pub struct TransferAuthorization {
+    pub token_info: Pubkey,
+    pub holder: Pubkey,
+    pub issued_by: Pubkey,
+    // Paid the account's rent, and gets it back on revocation
+    pub payer: Pubkey,
+    pub expires_at: Option<i64>,
+    // Most the holder may move per period, in each direction combined
+    pub period_limit: Option<u64>,
+    pub period_length: i64,
+    pub period_start: i64,
+    pub period_used: u64,
+    pub bump: u8,
+}
+
+impl TransferAuthorization {
+    pub const LEN: usize = 8 + 32 + 32 + 32 + 32 + 9 + 9 + 8 + 8 + 8 + 1;
+
+    // Remember who funded the account; a reissue keeps the original payer,
+    // who is owed the rent back on revocation
+    pub fn record_payer(&mut self, payer: Pubkey) {
+        if self.payer == Pubkey::default() {
+            self.payer = payer;
+        }
+    }
+
+    // Count `amount` against the authorization, rolling the period over first
+    pub fn record_transfer(&mut self, amount: u64, now: i64) -> Result<()> {
+        if self.expires_at.is_some_and(|expires_at| now >= expires_at) {
+            return Err(error!(TokenError::TransferNotAuthorized));
+        }
+
+        if let Some(period_limit) = self.period_limit {
+            if now >= self.period_start.saturating_add(self.period_length) {
+                self.period_start = now;
+                self.period_used = 0;
+            }
+            let used = self.period_used.checked_add(amount)
+                .ok_or(TokenError::TransferNotAuthorized)?;
+            if used > period_limit {
+                return Err(error!(TokenError::TransferNotAuthorized));
+            }
+            self.period_used = used;
+        }
+
+        Ok(())
+    }
+}
+
+#[event]
+pub struct TransferAuthorized {
+    pub token_info: Pubkey,
+    pub holder: Pubkey,
+    pub issued_by: Pubkey,
+    pub expires_at: Option<i64>,
+    pub period_limit: Option<u64>,
+}
+
+#[event]
+pub struct VestedTokensReleased {
+    pub token_info: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct TokenAuthorityTransferStarted {
+    pub token_info: Pubkey,
+    pub authority: Pubkey,
+    // None when a pending transfer was cancelled
+    pub pending_authority: Option<Pubkey>,
+}
+
+#[event]
+pub struct TokenAuthorityTransferred {
+    pub token_info: Pubkey,
+    pub previous_authority: Pubkey,
+    pub new_authority: Pubkey,
+}
+
+#[event]
+pub struct TokenAuthorityRenounced {
+    pub token_info: Pubkey,
+    pub previous_authority: Pubkey,
 }
 
 // Vesting schedule
@@ -92,6 +364,176 @@ pub struct VestingSchedule {
     pub interval: i64,
 }
 
+impl VestingSchedule {
+    pub const LEN: usize = 8 * 6;
+}
+
+// NFT collection grouping the non-fungible items of a project
+#[account]
+pub struct Collection {
+    // The project's current creator mints from the collection
+    pub project: Pubkey,
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub item_count: u64,
+    pub bump: u8,
+}
+
+impl Collection {
+    pub const LEN: usize = 8 + 32
+        + (4 + MAX_NAME_LEN) + (4 + MAX_SYMBOL_LEN) + (4 + MAX_URI_LEN)
+        + 8 + 1;
+}
+
+// Single NFT minted from a collection
+#[account]
+pub struct NftItem {
+    pub collection: Pubkey,
+    pub mint: Pubkey,
+    pub index: u64,
+    pub uri: String,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+impl NftItem {
+    pub const LEN: usize = 8 + 32 + 32 + 8 + (4 + MAX_URI_LEN) + 8 + 1;
+}
+
+// Soulbound credential issued to an approved collaborator. The credential
+// mint is frozen in the holder's wallet so it cannot be transferred.
+#[account]
+pub struct Credential {
+    pub project: Pubkey,
+    pub collaboration: Pubkey,
+    pub holder: Pubkey,
+    pub mint: Pubkey,
+    pub role: String,
+    pub issued_at: i64,
+    pub revoked_at: Option<i64>,
+    pub bump: u8,
+}
+
+impl Credential {
+    pub const LEN: usize = 8 + 32 + 32 + 32 + 32 + (4 + MAX_ROLE_LEN) + 8 + 9 + 1;
+
+    pub fn is_active(&self) -> bool {
+        self.revoked_at.is_none()
+    }
+
+    // Check that `token_account` holds this credential for `project`
+    pub fn verify(&self, project: &Pubkey, token_account: &TokenAccount) -> Result<()> {
+        if !self.is_active() {
+            return Err(error!(TokenError::CredentialRevoked));
+        }
+        if self.project != *project
+            || token_account.mint != self.mint
+            || token_account.owner != self.holder
+            || token_account.amount != 1
+        {
+            return Err(error!(TokenError::InvalidCredential));
+        }
+        Ok(())
+    }
+}
+
+// Ownership tokens minted against a resource. Whoever holds the mint's
+// tokens owns the matching share of the resource.
+#[account]
+pub struct ResourceToken {
+    pub resource: Pubkey,
+    pub mint: Pubkey,
+    pub token_type: TokenType,
+    pub total_shares: u64,
+    pub redeemed_shares: u64,
+    pub created_at: i64,
+    pub royalty: Option<RoyaltyConfig>,
+    pub bump: u8,
+}
+
+impl ResourceToken {
+    pub const LEN: usize = 8 + 32 + 32 + 1 + 8 + 8 + 8 + (1 + RoyaltyConfig::LEN) + 1;
+
+    pub fn outstanding_shares(&self) -> u64 {
+        self.total_shares.saturating_sub(self.redeemed_shares)
+    }
+
+    pub fn is_fully_redeemed(&self) -> bool {
+        self.redeemed_shares == self.total_shares
+    }
+}
+
+#[event]
+pub struct ResourceTokenized {
+    pub resource: Pubkey,
+    pub mint: Pubkey,
+    pub total_shares: u64,
+}
+
+#[event]
+pub struct ResourceRedeemed {
+    pub resource: Pubkey,
+    pub holder: Pubkey,
+    pub shares: u64,
+    pub consumed: bool,
+}
+
+#[event]
+pub struct HolderDiscrepancy {
+    pub token_info: Pubkey,
+    pub holder: Pubkey,
+    pub ledger_balance: u64,
+    pub spl_balance: u64,
+    pub resynced: bool,
+}
+
+#[event]
+pub struct SupplyDiscrepancy {
+    pub token_info: Pubkey,
+    pub ledger_supply: u64,
+    pub spl_supply: u64,
+    pub resynced: bool,
+}
+
+// Fixed-price sale of tokens, so creator royalties can be enforced. PDA at
+// ["listing", mint, seller]. The tokens stay in the seller's account with the
+// listing approved as SPL delegate for `remaining`. Project token listings
+// settle through TokenInfo::transfer between the seller's and buyer's
+// holders; resource token listings have no ledger. Collection NFTs carry no
+// TokenInfo or royalty config and cannot be listed.
+#[account]
+pub struct Listing {
+    pub seller: Pubkey,
+    pub mint: Pubkey,
+    pub payment_mint: Pubkey,
+    // The seller's token account the listing draws from
+    pub token_account: Pubkey,
+    // Set for project token listings, None for resource tokens
+    pub token_info: Option<Pubkey>,
+    // Price per base unit of `mint`, in base units of `payment_mint`
+    pub price: u64,
+    pub remaining: u64,
+    // Royalty terms of the listed token when the listing was created
+    pub royalty: Option<RoyaltyConfig>,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+impl Listing {
+    pub const LEN: usize = 8 + 32 + 32 + 32 + 32 + (1 + 32) + 8 + 8 + (1 + RoyaltyConfig::LEN) + 8 + 1;
+}
+
+#[event]
+pub struct RoyaltyPaid {
+    pub mint: Pubkey,
+    pub recipient: Pubkey,
+    pub seller: Pubkey,
+    pub buyer: Pubkey,
+    pub gross: u64,
+    pub royalty: u64,
+}
+
 // Token errors
 #[error_code]
 pub enum TokenError {
@@ -107,6 +549,54 @@ pub enum TokenError {
     InvalidVestingSchedule,
     #[msg("Unauthorized token operation")]
     UnauthorizedOperation,
+    #[msg("Token metadata field too long")]
+    MetadataTooLong,
+    #[msg("Credential does not match the holder or project")]
+    InvalidCredential,
+    #[msg("Credential has been revoked")]
+    CredentialRevoked,
+    #[msg("Holder still has a balance")]
+    HolderNotEmpty,
+    #[msg("Invalid royalty configuration")]
+    InvalidRoyalty,
+    #[msg("Listing requires the token's royalty source")]
+    MissingRoyaltySource,
+    #[msg("Transfer is not authorized for this holder")]
+    TransferNotAuthorized,
+    #[msg("Invalid distribution rules")]
+    InvalidDistributionRules,
+    #[msg("New authority must differ from the current one")]
+    InvalidNewAuthority,
+    #[msg("Signer is not the pending authority")]
+    NotPendingAuthority,
+    #[msg("An authority transfer is pending")]
+    AuthorityTransferPending,
+    #[msg("Holder already has a vesting schedule")]
+    VestingScheduleExists,
+    #[msg("Transfer limit period must be positive")]
+    InvalidTransferLimit,
+    #[msg("Token holder count overflow")]
+    HolderCountOverflow,
+    #[msg("No vested tokens to release")]
+    NothingVested,
+    #[msg("Listing requires the token's info account")]
+    MissingTokenInfo,
+}
+
+// Metadata strings are stored inline and must fit the reserved account space
+pub fn validate_metadata_fields(name: &str, symbol: &str, uri: &str) -> Result<()> {
+    if name.len() > MAX_NAME_LEN || symbol.len() > MAX_SYMBOL_LEN || uri.len() > MAX_URI_LEN {
+        return Err(error!(TokenError::MetadataTooLong));
+    }
+    Ok(())
+}
+
+// NFTs must be indivisible and unique
+pub fn validate_nft_config(metadata: &TokenMetadata, config: &TokenConfig) -> Result<()> {
+    if metadata.decimals != 0 || config.max_supply != Some(1) {
+        return Err(error!(TokenError::InvalidTokenType));
+    }
+    Ok(())
 }
 
 // Token interface methods
@@ -120,11 +610,22 @@ impl TokenInfo {
         permissions: TokenPermissions,
         authority: Pubkey,
     ) -> Result<()> {
+        validate_metadata_fields(&metadata.name, &metadata.symbol, &metadata.uri)?;
+        if let Some(royalty) = &config.royalty {
+            royalty.validate()?;
+        }
+        if metadata.token_type == TokenType::NFT {
+            validate_nft_config(&metadata, &config)?;
+        }
+        distribution_rules.validate()?;
+
         self.metadata = metadata;
+        self.metadata.created_at = Clock::get()?.unix_timestamp;
         self.config = config;
         self.distribution_rules = distribution_rules;
         self.permissions = permissions;
         self.authority = authority;
+        self.pending_authority = None;
         self.total_supply = 0;
         self.holder_count = 0;
         Ok(())
@@ -148,13 +649,103 @@ impl TokenInfo {
 
         self.total_supply = self.total_supply.checked_add(amount)
             .ok_or(TokenError::SupplyExceeded)?;
-        
+
+        let before = to.total_balance();
         to.balance = to.balance.checked_add(amount)
             .ok_or(TokenError::SupplyExceeded)?;
+        to.record_acquisition(before, Clock::get()?.unix_timestamp);
+        self.track_holder(before, to.total_balance())?;
+
+        Ok(())
+    }
+
+    pub fn start_authority_transfer(&mut self, new_authority: Pubkey) -> Result<()> {
+        if new_authority == self.authority || new_authority == Pubkey::default() {
+            return Err(error!(TokenError::InvalidNewAuthority));
+        }
+        self.pending_authority = Some(new_authority);
+        Ok(())
+    }
+
+    // Returns the previous authority
+    pub fn accept_authority(&mut self, new_authority: Pubkey) -> Result<Pubkey> {
+        if self.pending_authority != Some(new_authority) {
+            return Err(error!(TokenError::NotPendingAuthority));
+        }
+        let previous = self.authority;
+        self.authority = new_authority;
+        self.pending_authority = None;
+        Ok(previous)
+    }
+
+    // Give up the authority for good; nothing can mint or change the token
+    // afterwards. Returns the previous authority.
+    pub fn renounce_authority(&mut self) -> Result<Pubkey> {
+        if self.pending_authority.is_some() {
+            return Err(error!(TokenError::AuthorityTransferPending));
+        }
+        let previous = self.authority;
+        self.authority = Pubkey::default();
+        self.config.is_mintable = false;
+        self.permissions.can_mint = false;
+        self.permissions.can_modify_metadata = false;
+        Ok(previous)
+    }
+
+    // Account for tokens minted into a program vault ahead of distribution
+    pub fn reserve_supply(&mut self, amount: u64) -> Result<()> {
+        if !self.config.is_mintable {
+            return Err(error!(TokenError::UnauthorizedOperation));
+        }
+
+        let total_supply = self.total_supply.checked_add(amount)
+            .ok_or(TokenError::SupplyExceeded)?;
+        if self.config.max_supply.is_some_and(|max_supply| total_supply > max_supply) {
+            return Err(error!(TokenError::SupplyExceeded));
+        }
+        self.total_supply = total_supply;
+
+        Ok(())
+    }
 
+    // Give back reserved tokens that were burned from the program vault
+    pub fn release_reserved(&mut self, amount: u64) -> Result<()> {
+        self.total_supply = self.total_supply.checked_sub(amount)
+            .ok_or(TokenError::InsufficientBalance)?;
         Ok(())
     }
 
+    // Credit reserved tokens to a holder, locking them under the token's
+    // vesting rules when it has any
+    pub fn distribute_reserved(
+        &mut self,
+        amount: u64,
+        to: &mut TokenHolder,
+        vesting_start: i64,
+    ) -> Result<()> {
+        let before = to.total_balance();
+
+        match self.distribution_rules.vesting_schedule(vesting_start, amount)? {
+            Some(schedule) => {
+                // Folding a second grant into the schedule would rebase its
+                // vesting on the first grant's start and cliff
+                if to.vesting_schedule.is_some() {
+                    return Err(error!(TokenError::VestingScheduleExists));
+                }
+                to.vesting_schedule = Some(schedule);
+                to.locked_balance = to.locked_balance.checked_add(amount)
+                    .ok_or(TokenError::SupplyExceeded)?;
+            }
+            None => {
+                to.balance = to.balance.checked_add(amount)
+                    .ok_or(TokenError::SupplyExceeded)?;
+            }
+        }
+
+        to.record_acquisition(before, vesting_start);
+        self.track_holder(before, to.total_balance())
+    }
+
     // Burn tokens
     pub fn burn(
         &mut self,
@@ -171,36 +762,49 @@ impl TokenInfo {
 
         self.total_supply = self.total_supply.checked_sub(amount)
             .ok_or(TokenError::InsufficientBalance)?;
-        
+
+        let before = from.total_balance();
         from.balance = from.balance.checked_sub(amount)
             .ok_or(TokenError::InsufficientBalance)?;
+        self.track_holder(before, from.total_balance())?;
 
         Ok(())
     }
 
-    // Transfer tokens
+    // Transfer tokens. Tokens that require authorization need the sender's
+    // and receiver's TransferAuthorization, in that order.
     pub fn transfer(
-        &self,
+        &mut self,
         amount: u64,
         from: &mut TokenHolder,
         to: &mut TokenHolder,
+        authorizations: Option<(&mut TransferAuthorization, &mut TransferAuthorization)>,
+        now: i64,
     ) -> Result<()> {
         if !self.config.is_transferable {
             return Err(error!(TokenError::UnauthorizedOperation));
         }
 
+        if self.config.requires_auth {
+            let (from_auth, to_auth) = authorizations
+                .ok_or(TokenError::TransferNotAuthorized)?;
+            if from_auth.holder != from.owner || to_auth.holder != to.owner {
+                return Err(error!(TokenError::TransferNotAuthorized));
+            }
+            from_auth.record_transfer(amount, now)?;
+            to_auth.record_transfer(amount, now)?;
+        }
+
         // Check transfer lock
-        if let Some(lock_period) = self.distribution_rules.transfer_lock {
-            let current_time = Clock::get()?.unix_timestamp;
-            if current_time < lock_period {
+        if let Some(lock) = &self.distribution_rules.transfer_lock {
+            if now < lock.unlocks_at(self.metadata.created_at, from.acquired_at) {
                 return Err(error!(TokenError::TransferLocked));
             }
         }
 
         // Check minimum hold period
         if let Some(min_hold) = self.distribution_rules.min_hold_period {
-            let current_time = Clock::get()?.unix_timestamp;
-            if current_time - from.last_transfer < min_hold {
+            if !from.held_for(min_hold, now) {
                 return Err(error!(TokenError::TransferLocked));
             }
         }
@@ -209,23 +813,72 @@ impl TokenInfo {
             return Err(error!(TokenError::InsufficientBalance));
         }
 
+        let from_before = from.total_balance();
+        let to_before = to.total_balance();
+
         from.balance = from.balance.checked_sub(amount)
             .ok_or(TokenError::InsufficientBalance)?;
         
         to.balance = to.balance.checked_add(amount)
             .ok_or(TokenError::SupplyExceeded)?;
+        to.record_acquisition(to_before, now);
+
+        self.track_holder(from_before, from.total_balance())?;
+        self.track_holder(to_before, to.total_balance())?;
 
-        from.last_transfer = Clock::get()?.unix_timestamp;
+        from.last_transfer = now;
 
         Ok(())
     }
 
-    // Update vesting schedule
+    // Shadow holder balance, if it disagrees with the SPL token account amount
+    pub fn holder_discrepancy(holder: &TokenHolder, spl_amount: u64) -> Option<u64> {
+        let ledger = holder.total_balance();
+        (ledger != spl_amount).then_some(ledger)
+    }
+
+    // Shadow total supply, if it disagrees with the SPL mint supply
+    pub fn supply_discrepancy(&self, mint_supply: u64) -> Option<u64> {
+        (self.total_supply != mint_supply).then_some(self.total_supply)
+    }
+
+    // Treat the SPL token account as the source of truth for a holder.
+    // Locked tokens are kept locked; the spendable balance absorbs the difference.
+    pub fn resync_holder(&mut self, holder: &mut TokenHolder, spl_amount: u64) -> Result<()> {
+        let before = holder.total_balance();
+        holder.locked_balance = holder.locked_balance.min(spl_amount);
+        holder.balance = spl_amount - holder.locked_balance;
+        holder.record_acquisition(before, Clock::get()?.unix_timestamp);
+        self.track_holder(before, holder.total_balance())
+    }
+
+    // Treat the SPL mint as the source of truth for total supply
+    pub fn resync_supply(&mut self, mint_supply: u64) {
+        self.total_supply = mint_supply;
+    }
+
+    // Keep holder_count equal to the number of holders with a nonzero balance
+    fn track_holder(&mut self, before: u64, after: u64) -> Result<()> {
+        if before == 0 && after > 0 {
+            self.holder_count = self.holder_count.checked_add(1)
+                .ok_or(TokenError::HolderCountOverflow)?;
+        } else if before > 0 && after == 0 {
+            self.holder_count = self.holder_count.checked_sub(1)
+                .ok_or(TokenError::InsufficientBalance)?;
+        }
+        Ok(())
+    }
+
+    // Set a vesting schedule on a holder that has none; an existing schedule
+    // is never replaced, so locked tokens keep the terms they were granted on
     pub fn update_vesting(
         &self,
         holder: &mut TokenHolder,
         schedule: VestingSchedule,
     ) -> Result<()> {
+        if holder.vesting_schedule.is_some() {
+            return Err(error!(TokenError::VestingScheduleExists));
+        }
         if schedule.end_time <= schedule.start_time {
             return Err(error!(TokenError::InvalidVestingSchedule));
         }
@@ -234,42 +887,393 @@ impl TokenInfo {
         Ok(())
     }
 
-    // Release vested tokens
+    // Move the part of a holder's schedule vested by `now` from the locked to
+    // the spendable balance, returning the amount released
     pub fn release_vested(
         &self,
         holder: &mut TokenHolder,
-    ) -> Result<()> {
-        if let Some(schedule) = &mut holder.vesting_schedule {
-            let current_time = Clock::get()?.unix_timestamp;
-            
-            // Check cliff period
-            if current_time < schedule.cliff_time {
-                return Ok(());
-            }
+        now: i64,
+    ) -> Result<u64> {
+        let Some(schedule) = &mut holder.vesting_schedule else {
+            return Err(error!(TokenError::NothingVested));
+        };
+        if now < schedule.cliff_time {
+            return Err(error!(TokenError::NothingVested));
+        }
 
-            // Calculate vested amount
-            let vesting_duration = schedule.end_time - schedule.start_time;
-            let elapsed = current_time - schedule.start_time;
-            let vested_amount = (schedule.total_amount as f64 * elapsed as f64 
-                / vesting_duration as f64) as u64;
-            
-            let releasable = vested_amount.checked_sub(schedule.released_amount)
-                .ok_or(TokenError::InvalidVestingSchedule)?;
-            
-            if releasable > 0 {
-                holder.locked_balance = holder.locked_balance
-                    .checked_sub(releasable)
-                    .ok_or(TokenError::InsufficientBalance)?;
-                
-                holder.balance = holder.balance
-                    .checked_add(releasable)
-                    .ok_or(TokenError::SupplyExceeded)?;
-                
-                schedule.released_amount = schedule.released_amount
-                    .checked_add(releasable)
-                    .ok_or(TokenError::SupplyExceeded)?;
-            }
+        // Linear vesting over the schedule, capped at the total once it ends
+        let vesting_duration = (schedule.end_time - schedule.start_time) as u128;
+        let elapsed = (now.min(schedule.end_time) - schedule.start_time).max(0) as u128;
+        let vested_amount = (schedule.total_amount as u128 * elapsed / vesting_duration) as u64;
+
+        let releasable = vested_amount.checked_sub(schedule.released_amount)
+            .ok_or(TokenError::InvalidVestingSchedule)?;
+        if releasable == 0 {
+            return Err(error!(TokenError::NothingVested));
         }
-        Ok(())
+
+        holder.locked_balance = holder.locked_balance
+            .checked_sub(releasable)
+            .ok_or(TokenError::InsufficientBalance)?;
+        holder.balance = holder.balance
+            .checked_add(releasable)
+            .ok_or(TokenError::SupplyExceeded)?;
+        schedule.released_amount = schedule.released_amount
+            .checked_add(releasable)
+            .ok_or(TokenError::SupplyExceeded)?;
+
+        Ok(releasable)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(token_type: TokenType, decimals: u8) -> TokenMetadata {
+        TokenMetadata {
+            name: "Token".to_string(),
+            symbol: "TKN".to_string(),
+            uri: String::new(),
+            decimals,
+            token_type,
+            creator: Pubkey::default(),
+            is_frozen: false,
+            created_at: 0,
+        }
+    }
+
+    fn config(max_supply: Option<u64>) -> TokenConfig {
+        TokenConfig {
+            initial_supply: 0,
+            max_supply,
+            is_mintable: true,
+            is_burnable: true,
+            is_transferable: true,
+            requires_auth: false,
+            royalty: None,
+        }
+    }
+
+    fn token_info(distribution_rules: DistributionRules) -> TokenInfo {
+        TokenInfo {
+            mint: Pubkey::default(),
+            project: None,
+            metadata: metadata(TokenType::ProjectToken, 6),
+            config: config(None),
+            distribution_rules,
+            permissions: TokenPermissions {
+                can_mint: true,
+                can_burn: true,
+                can_freeze: false,
+                can_modify_metadata: true,
+            },
+            total_supply: 0,
+            holder_count: 0,
+            authority: Pubkey::default(),
+            pending_authority: None,
+            bump: 0,
+        }
+    }
+
+    fn holder() -> TokenHolder {
+        TokenHolder {
+            owner: Pubkey::new_unique(),
+            token_info: Pubkey::default(),
+            token_account: Pubkey::default(),
+            balance: 0,
+            locked_balance: 0,
+            vesting_schedule: None,
+            last_transfer: 0,
+            acquired_at: 0,
+            avg_acquired_at: 0,
+            bump: 0,
+        }
+    }
+
+    fn vesting_rules() -> DistributionRules {
+        DistributionRules {
+            vesting_period: Some(1_000),
+            cliff_period: Some(100),
+            transfer_lock: None,
+            min_hold_period: None,
+        }
+    }
+
+    #[test]
+    fn reserved_tokens_vest_from_distribution() {
+        let mut token_info = token_info(vesting_rules());
+        token_info.reserve_supply(500).unwrap();
+        let mut holder = holder();
+
+        token_info.distribute_reserved(500, &mut holder, 10).unwrap();
+        assert_eq!(holder.balance, 0);
+        assert_eq!(holder.locked_balance, 500);
+        let schedule = holder.vesting_schedule.as_ref().unwrap();
+        assert_eq!(schedule.total_amount, 500);
+        assert_eq!((schedule.start_time, schedule.cliff_time, schedule.end_time), (10, 110, 1_010));
+        assert_eq!(token_info.holder_count, 1);
+    }
+
+    #[test]
+    fn second_vesting_grant_is_rejected() {
+        let mut token_info = token_info(vesting_rules());
+        token_info.reserve_supply(800).unwrap();
+        let mut holder = holder();
+
+        token_info.distribute_reserved(500, &mut holder, 10).unwrap();
+        assert!(token_info.distribute_reserved(300, &mut holder, 5_000).is_err());
+        assert_eq!(holder.locked_balance, 500);
+        assert_eq!(holder.vesting_schedule.as_ref().unwrap().start_time, 10);
+    }
+
+    #[test]
+    fn unvested_grants_credit_the_balance() {
+        let rules = DistributionRules {
+            vesting_period: None,
+            cliff_period: None,
+            transfer_lock: None,
+            min_hold_period: None,
+        };
+        let mut token_info = token_info(rules);
+        let mut holder = holder();
+
+        token_info.distribute_reserved(200, &mut holder, 10).unwrap();
+        token_info.distribute_reserved(200, &mut holder, 20).unwrap();
+        assert_eq!(holder.balance, 400);
+        assert!(holder.vesting_schedule.is_none());
+    }
+
+    #[test]
+    fn holder_count_overflow_is_reported() {
+        let mut token_info = token_info(DistributionRules {
+            vesting_period: None,
+            cliff_period: None,
+            transfer_lock: None,
+            min_hold_period: None,
+        });
+        token_info.holder_count = u64::MAX;
+        assert_eq!(
+            token_info.distribute_reserved(1, &mut holder(), 0),
+            Err(error!(TokenError::HolderCountOverflow))
+        );
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn vested_tokens_release_over_time_and_become_transferable() {
+        let mut token_info = token_info(vesting_rules());
+        token_info.reserve_supply(1_000).unwrap();
+        let mut grantee = holder();
+        let mut receiver = holder();
+
+        // Claimed at 0: cliff at 100, fully vested at 1_000
+        token_info.distribute_reserved(1_000, &mut grantee, 0).unwrap();
+        assert_eq!(token_info.release_vested(&mut grantee, 99), Err(error!(TokenError::NothingVested)));
+        assert!(token_info.transfer(1, &mut grantee, &mut receiver, None, 99).is_err());
+
+        assert_eq!(token_info.release_vested(&mut grantee, 250).unwrap(), 250);
+        assert_eq!((grantee.balance, grantee.locked_balance), (250, 750));
+        assert_eq!(token_info.release_vested(&mut grantee, 250), Err(error!(TokenError::NothingVested)));
+
+        token_info.transfer(250, &mut grantee, &mut receiver, None, 300).unwrap();
+        assert_eq!((grantee.balance, receiver.balance), (0, 250));
+        assert!(token_info.transfer(1, &mut grantee, &mut receiver, None, 300).is_err());
+
+        // Past the end only the remaining total is released
+        assert_eq!(token_info.release_vested(&mut grantee, 5_000).unwrap(), 750);
+        assert_eq!((grantee.balance, grantee.locked_balance), (750, 0));
+        assert_eq!(token_info.holder_count, 2);
+    }
+
+    #[test]
+    fn release_without_schedule_is_rejected() {
+        let token_info = token_info(vesting_rules());
+        assert_eq!(token_info.release_vested(&mut holder(), 100), Err(error!(TokenError::NothingVested)));
+    }
+
+    #[test]
+    fn vesting_update_cannot_replace_a_schedule() {
+        let mut token_info = token_info(vesting_rules());
+        token_info.reserve_supply(500).unwrap();
+        let mut grantee = holder();
+        let schedule = VestingSchedule {
+            total_amount: 500,
+            released_amount: 0,
+            start_time: 0,
+            end_time: 10,
+            cliff_time: 0,
+            interval: 0,
+        };
+
+        assert_eq!(
+            token_info.update_vesting(&mut holder(), VestingSchedule { end_time: 0, ..schedule.clone() }),
+            Err(error!(TokenError::InvalidVestingSchedule))
+        );
+
+        token_info.distribute_reserved(500, &mut grantee, 10).unwrap();
+        assert_eq!(
+            token_info.update_vesting(&mut grantee, schedule),
+            Err(error!(TokenError::VestingScheduleExists))
+        );
+        assert_eq!(grantee.vesting_schedule.as_ref().unwrap().start_time, 10);
+    }
+
+    #[test]
+    fn reissued_authorization_keeps_its_payer() {
+        let first = Pubkey::new_unique();
+        let mut authorization = TransferAuthorization {
+            token_info: Pubkey::default(),
+            holder: Pubkey::new_unique(),
+            issued_by: first,
+            payer: Pubkey::default(),
+            expires_at: None,
+            period_limit: None,
+            period_length: 0,
+            period_start: 0,
+            period_used: 0,
+            bump: 0,
+        };
+
+        authorization.record_payer(first);
+        authorization.record_payer(Pubkey::new_unique());
+        assert_eq!(authorization.payer, first);
+    }
+
+    #[test]
+    fn releasing_reserved_supply() {
+        let mut token_info = token_info(vesting_rules());
+        token_info.reserve_supply(1_000).unwrap();
+        token_info.release_reserved(400).unwrap();
+        assert_eq!(token_info.total_supply, 600);
+        assert!(token_info.release_reserved(601).is_err());
+    }
+
+    #[test]
+    fn transfer_lock_bases() {
+        let lock = |base, value| TransferLock { base, value };
+        assert_eq!(lock(LockBase::Absolute, 5_000).unlocks_at(100, 200), 5_000);
+        assert_eq!(lock(LockBase::TokenCreation, 1_000).unlocks_at(100, 200), 1_100);
+        assert_eq!(lock(LockBase::HolderAcquisition, 1_000).unlocks_at(100, 200), 1_200);
+
+        // Relative locks saturate instead of wrapping
+        assert_eq!(lock(LockBase::TokenCreation, i64::MAX).unlocks_at(100, 200), i64::MAX);
+        assert_eq!(lock(LockBase::HolderAcquisition, i64::MAX).unlocks_at(100, 200), i64::MAX);
+    }
+
+    #[test]
+    fn distribution_rules_validation() {
+        assert!(vesting_rules().validate().is_ok());
+
+        let cliff_only = DistributionRules { vesting_period: None, ..vesting_rules() };
+        assert!(cliff_only.validate().is_err());
+        let cliff_past_end = DistributionRules { cliff_period: Some(1_001), ..vesting_rules() };
+        assert!(cliff_past_end.validate().is_err());
+        let zero_lock = DistributionRules {
+            transfer_lock: Some(TransferLock { base: LockBase::TokenCreation, value: 0 }),
+            ..vesting_rules()
+        };
+        assert!(zero_lock.validate().is_err());
+        let zero_hold = DistributionRules { min_hold_period: Some(0), ..vesting_rules() };
+        assert!(zero_hold.validate().is_err());
+    }
+
+    #[test]
+    fn acquisition_time_is_balance_weighted() {
+        let mut holder = holder();
+
+        holder.balance = 100;
+        holder.record_acquisition(0, 1_000);
+        assert_eq!((holder.acquired_at, holder.avg_acquired_at), (1_000, 1_000));
+
+        // 100 tokens aged from 1_000 and 300 new ones at 2_000
+        holder.balance = 400;
+        holder.record_acquisition(100, 2_000);
+        assert_eq!(holder.acquired_at, 1_000);
+        assert_eq!(holder.avg_acquired_at, 1_750);
+
+        // Debits leave the stamps alone
+        holder.balance = 50;
+        holder.record_acquisition(400, 3_000);
+        assert_eq!((holder.acquired_at, holder.avg_acquired_at), (1_000, 1_750));
+
+        // Emptying and refilling restarts the clock
+        holder.balance = 10;
+        holder.record_acquisition(0, 4_000);
+        assert_eq!((holder.acquired_at, holder.avg_acquired_at), (4_000, 4_000));
+    }
+
+    #[test]
+    fn locked_tokens_count_towards_acquisition() {
+        let mut holder = holder();
+        holder.balance = 100;
+        holder.record_acquisition(0, 0);
+        holder.locked_balance = 100;
+        holder.record_acquisition(100, 1_000);
+        assert_eq!(holder.avg_acquired_at, 500);
+    }
+
+    #[test]
+    fn held_for_measures_average_age() {
+        let mut holder = holder();
+        holder.avg_acquired_at = 1_000;
+        assert!(!holder.held_for(500, 1_499));
+        assert!(holder.held_for(500, 1_500));
+        assert!(holder.held_for(0, 1_000));
+        assert!(!holder.held_for(1, i64::MIN));
+    }
+
+    #[test]
+    fn token_authority_moves_in_two_steps() {
+        let authority = Pubkey::new_unique();
+        let new_authority = Pubkey::new_unique();
+        let mut token_info = token_info(vesting_rules());
+        token_info.authority = authority;
+
+        assert!(token_info.start_authority_transfer(authority).is_err());
+        assert!(token_info.start_authority_transfer(Pubkey::default()).is_err());
+        token_info.start_authority_transfer(new_authority).unwrap();
+        assert!(token_info.accept_authority(Pubkey::new_unique()).is_err());
+
+        assert_eq!(token_info.accept_authority(new_authority).unwrap(), authority);
+        assert_eq!(token_info.authority, new_authority);
+        assert_eq!(token_info.pending_authority, None);
+        assert!(token_info.accept_authority(new_authority).is_err());
+    }
+
+    #[test]
+    fn renouncing_locks_the_token() {
+        let authority = Pubkey::new_unique();
+        let mut token_info = token_info(vesting_rules());
+        token_info.authority = authority;
+
+        // A pending transfer must be resolved first
+        token_info.start_authority_transfer(Pubkey::new_unique()).unwrap();
+        assert!(token_info.renounce_authority().is_err());
+        token_info.pending_authority = None;
+
+        assert_eq!(token_info.renounce_authority().unwrap(), authority);
+        assert_eq!(token_info.authority, Pubkey::default());
+        assert!(!token_info.config.is_mintable);
+        assert!(!token_info.permissions.can_mint);
+        assert!(!token_info.permissions.can_modify_metadata);
+        assert!(token_info.reserve_supply(1).is_err());
+        assert!(token_info.accept_authority(Pubkey::default()).is_err());
+    }
+
+    #[test]
+    fn nft_config_must_be_indivisible_and_unique() {
+        let nft = metadata(TokenType::NFT, 0);
+        assert!(validate_nft_config(&nft, &config(Some(1))).is_ok());
+        assert!(validate_nft_config(&nft, &config(Some(2))).is_err());
+        assert!(validate_nft_config(&nft, &config(None)).is_err());
+        assert!(validate_nft_config(&metadata(TokenType::NFT, 6), &config(Some(1))).is_err());
+    }
+
+    #[test]
+    fn metadata_fields_must_fit_reserved_space() {
+        assert!(validate_metadata_fields("Token", "TKN", "").is_ok());
+        assert!(validate_metadata_fields(&"n".repeat(MAX_NAME_LEN + 1), "TKN", "").is_err());
+        assert!(validate_metadata_fields("Token", &"s".repeat(MAX_SYMBOL_LEN + 1), "").is_err());
+        assert!(validate_metadata_fields("Token", "TKN", &"u".repeat(MAX_URI_LEN + 1)).is_err());
+    }
+}